@@ -0,0 +1,93 @@
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use string_builder::Builder;
+
+use crate::commands::command::Command;
+use crate::commands::permissions::check_manage_guild;
+use crate::hypixel::cache::{self, Endpoint, ENDPOINTS};
+use crate::say_something;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        if !check_manage_guild(ctx, msg).await {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+
+        let args: Vec<&str> = msg.content.split_whitespace().skip(1).collect();
+        //no arguments shows what is cached
+        if args.is_empty() {
+            let mut message = Builder::default();
+            for endpoint in ENDPOINTS.iter() {
+                let count = cache::entry_count(*endpoint);
+                if count.is_err() {
+                    send_err(ctx, msg).await;
+                    return;
+                }
+                message.append(format!(
+                    "{}: {} entries, fresh for {} seconds\n",
+                    endpoint.name(),
+                    count.unwrap(),
+                    endpoint.ttl()
+                ));
+            }
+            let stats = cache::stats();
+            message.append(format!(
+                "Since the start: {} hits, {} misses, {} stale answers because the API failed",
+                stats.hits, stats.misses, stats.stale_hits
+            ));
+            say_something(message.string().unwrap(), ctx, msg).await;
+            return;
+        }
+
+        //`flush [endpoint]`, without an endpoint everything is flushed
+        let endpoints: Option<Vec<Endpoint>> = match args.as_slice() {
+            ["flush"] => Some(ENDPOINTS.to_vec()),
+            ["flush", endpoint] => Endpoint::from_arg(endpoint).map(|endpoint| vec![endpoint]),
+            _ => None,
+        };
+        if endpoints.is_none() {
+            say_something(
+                format!(
+                    "Invalid usage: `{}` or `{} flush [profile|player|guild]`",
+                    command, command
+                ),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+        let mut flushed = 0;
+        for endpoint in endpoints.unwrap() {
+            let count = cache::flush(endpoint);
+            if count.is_err() {
+                send_err(ctx, msg).await;
+                return;
+            }
+            flushed += count.unwrap();
+        }
+        say_something(format!("Flushed {} cached responses.", flushed), ctx, msg).await;
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
@@ -0,0 +1,118 @@
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+
+use crate::commands::command::Command;
+use crate::commands::permissions::check_manage_guild;
+use crate::features::backfill;
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        if !check_manage_guild(ctx, msg).await {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        let guild_id = msg.guild_id.unwrap().0;
+
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let mut con = con.unwrap();
+
+        let args: Vec<&str> = msg.content.split_whitespace().skip(1).collect();
+        if args.first() == Some(&"status") {
+            let progress = backfill::get_progress(&mut con, guild_id);
+            if progress.is_err() {
+                send_err(ctx, msg).await;
+                return;
+            }
+            let progress = progress.unwrap();
+            say_something(
+                format!(
+                    "Backfill {}: {} pages read, {} messages counted, {} ranges of history left.",
+                    progress.state, progress.pages, progress.counted, progress.pending_gaps
+                ),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+
+        //every argument has to be a channel mention, no arguments resumes everything that is pending
+        let mut channels = Vec::new();
+        for arg in &args {
+            match arg
+                .strip_prefix("<#")
+                .and_then(|arg| arg.strip_suffix('>'))
+                .and_then(|id| id.parse::<u64>().ok())
+            {
+                Some(channel_id) => channels.push(channel_id),
+                None => {
+                    say_something(
+                        format!(
+                            "Invalid usage: `{} [#channel...]` or `{} status`",
+                            command, command
+                        ),
+                        ctx,
+                        msg,
+                    )
+                    .await;
+                    return;
+                }
+            }
+        }
+        if backfill::prepare_channels(&mut con, guild_id, &channels).is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        if !backfill::try_start(guild_id) {
+            say_something(
+                format!(
+                    "A backfill is already running for this Server, check `{} status`.",
+                    command
+                ),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+
+        let status = msg.channel_id.say(&ctx.http, "Starting backfill...").await;
+        if status.is_err() {
+            backfill::release(guild_id);
+            return;
+        }
+        let channels = if channels.is_empty() {
+            None
+        } else {
+            Some(channels)
+        };
+        tokio::spawn(backfill::run(ctx, guild_id, channels, status.unwrap()));
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
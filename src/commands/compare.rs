@@ -0,0 +1,187 @@
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
+use string_builder::Builder;
+
+use crate::commands::command::Command;
+use crate::commands::hypixel_queue::announce_position;
+use crate::commands::members::{current_members, display_label, minecraft_identity};
+use crate::commands::resolve::resolve_member_arg;
+use crate::features::links;
+use crate::features::member_stats::{self, MemberStats};
+use crate::features::periods::Period;
+use crate::features::voice_tracking;
+use crate::hypixel::cache::Endpoint;
+use crate::hypixel::models::Player;
+use crate::say_something;
+use crate::{HYPIXEL_CLIENT, REDIS_CLIENT};
+
+//periods compared, in the order they are shown
+const PERIODS: [Period; 4] = [Period::Week, Period::Month, Period::Season, Period::AllTime];
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+
+        let args: Vec<&str> = msg.content.split_whitespace().skip(1).collect();
+        if args.len() != 2 {
+            say_something(
+                format!("Invalid usage: `{} <user> <user>`", command),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+        let mut member_ids = Vec::new();
+        for arg in args {
+            match resolve_member_arg(&ctx, &msg, arg).await {
+                Some(member_id) => member_ids.push(member_id),
+                None => return,
+            }
+        }
+
+        let guild_id = msg.guild_id.unwrap();
+        let members = current_members(&ctx, guild_id).await;
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() || members.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let mut con = con.unwrap();
+        let members: Vec<u64> = members
+            .unwrap()
+            .iter()
+            .map(|member| member.user.id.0)
+            .collect();
+        let mut columns = Vec::new();
+        for member_id in &member_ids {
+            let stats = member_stats::read(&mut con, guild_id.0, *member_id, &PERIODS, 0, &members);
+            if stats.is_err() {
+                send_err(ctx, msg).await;
+                return;
+            }
+            columns.push(
+                self.column(&ctx, &msg, guild_id, *member_id, stats.unwrap())
+                    .await,
+            );
+        }
+
+        let _ = msg
+            .channel_id
+            .send_message(&ctx.http, |m| {
+                m.embed(|e| {
+                    e.title("Member comparison");
+                    for (name, value) in columns {
+                        e.field(name, value, true);
+                    }
+                    e
+                })
+            })
+            .await;
+    }
+}
+
+impl CommandArgs {
+    ///Name and embed field content for one member.
+    async fn column(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        guild_id: GuildId,
+        member_id: u64,
+        stats: MemberStats,
+    ) -> (String, String) {
+        let mut builder = Builder::default();
+        for (period, standing) in &stats.standings {
+            match standing {
+                Some(standing) => builder.append(format!(
+                    "{}: {} (#{})\n",
+                    period.name(),
+                    standing.messages,
+                    standing.rank
+                )),
+                None => builder.append(format!("{}: 0\n", period.name())),
+            }
+        }
+        builder.append(format!(
+            "Streak: {} days (longest: {} days)\nVoice: {}\n",
+            stats.streak.current,
+            stats.streak.longest,
+            voice_tracking::format_duration(stats.voice)
+        ));
+
+        let member = guild_id.member(&ctx, member_id).await;
+        let guild = guild_id.to_guild_cached(&ctx).await;
+        let (member, guild) = match (member, guild) {
+            (Ok(member), Some(guild)) => (member, guild),
+            _ => return ("Unknown user".to_string(), builder.string().unwrap()),
+        };
+        //None if redis failed, the nickname of a linked member mustn't be taken for their account then
+        let link = REDIS_CLIENT
+            .get_connection()
+            .and_then(|mut con| links::get_link(&mut con, guild_id.0, member_id))
+            .ok();
+        let minecraft = link
+            .as_ref()
+            .and_then(|link| minecraft_identity(&guild, &member, link.as_ref()));
+        //Hypixel stats are only known for verified members
+        if let Some(minecraft) = minecraft {
+            //members that verified before links were stored need a Mojang lookup for the uuid
+            let uuid = match link.as_ref().and_then(|link| link.as_ref()) {
+                Some(link) => Some(link.uuid.clone()),
+                None => HYPIXEL_CLIENT
+                    .profile(&minecraft.username)
+                    .await
+                    .ok()
+                    .map(|profile| profile.id),
+            };
+            let player = match uuid {
+                Some(uuid) => {
+                    announce_position(ctx, msg, &uuid, &[Endpoint::Player], "comparison").await;
+                    HYPIXEL_CLIENT.player(&uuid, guild_id.0).await.ok()
+                }
+                None => None,
+            };
+            match player {
+                Some(player) => builder.append(format_hypixel_stats(&player)),
+                None => builder.append("Hypixel stats unavailable\n"),
+            }
+        }
+        let link = link.flatten();
+        (
+            display_label(&guild, &member, link.as_ref()),
+            builder.string().unwrap(),
+        )
+    }
+}
+
+fn format_hypixel_stats(player: &Player) -> String {
+    format!(
+        "Network level: {:.2}\nKarma: {}\nAchievement points: {}\n",
+        player.network_level(),
+        player.karma.unwrap_or(0),
+        player.achievement_points.unwrap_or(0)
+    )
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
@@ -0,0 +1,31 @@
+use serenity::client::Context;
+use serenity::model::channel::Message;
+
+use crate::hypixel::cache::Endpoint;
+use crate::say_something;
+use crate::HYPIXEL_CLIENT;
+
+///Tells the author where their lookup is queued if the quota of the API key is used up, `what` names the lookup.
+///Nothing is sent if the answers are cached. Returns whether the lookup is queued.
+pub async fn announce_position(
+    ctx: &Context,
+    msg: &Message,
+    uuid: &str,
+    endpoints: &[Endpoint],
+    what: &str,
+) -> bool {
+    let guild_id = msg.guild_id.map_or(0, |guild_id| guild_id.0);
+    let position = HYPIXEL_CLIENT.queue_position(guild_id, uuid, endpoints);
+    if let Some(position) = position {
+        say_something(
+            format!(
+                "The Hypixel API limit is reached, your {} is queued, position {}.",
+                what, position
+            ),
+            ctx.clone(),
+            msg.clone(),
+        )
+        .await;
+    }
+    position.is_some()
+}
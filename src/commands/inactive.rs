@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+use redis::{Commands, RedisResult};
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::http::AttachmentType;
+use serenity::model::channel::Message;
+use string_builder::Builder;
+
+use crate::commands::command::Command;
+use crate::commands::members::{fetch_all_members, find_role};
+use crate::commands::permissions::check_manage_guild;
+use crate::features::message_counting::last_seen_key;
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+//members listed in the embed, the attached CSV always contains everyone
+const SHOWN_MEMBERS: usize = 20;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+struct InactiveMember {
+    id: u64,
+    name: String,
+    last_seen: Option<i64>,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        if !check_manage_guild(ctx, msg).await {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        let guild_id = msg.guild_id.unwrap();
+
+        let args: Vec<&str> = msg.content.split_whitespace().skip(1).collect();
+        let days = args.first().and_then(|days| days.parse::<i64>().ok());
+        if days.is_none() || days.unwrap() < 1 {
+            say_something(
+                format!("Invalid usage: `{} days [role]`", command),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+        let days = days.unwrap();
+
+        //optional role filter, the name may contain spaces
+        let mut role = None;
+        if args.len() > 1 {
+            let role_arg = args[1..].join(" ");
+            let guild = guild_id.to_guild_cached(&ctx).await;
+            role = guild.and_then(|guild| find_role(&guild, &role_arg));
+            if role.is_none() {
+                say_something(format!("Unknown role `{}`", role_arg), ctx, msg).await;
+                return;
+            }
+        }
+
+        let members = fetch_all_members(&ctx, guild_id).await;
+        if members.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let last_seen: RedisResult<Vec<(u64, i64)>> =
+            con.unwrap()
+                .zrange_withscores(last_seen_key(guild_id.0), 0, -1);
+        if last_seen.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let last_seen: HashMap<u64, i64> = last_seen.unwrap().into_iter().collect();
+
+        let cutoff = Utc::now().timestamp() - days * 86400;
+        let mut inactive: Vec<InactiveMember> = members
+            .unwrap()
+            .into_iter()
+            .filter(|member| !member.user.bot)
+            .filter(|member| match role {
+                Some(role) => member.roles.contains(&role),
+                None => true,
+            })
+            .map(|member| InactiveMember {
+                id: member.user.id.0,
+                name: member.display_name().to_string(),
+                last_seen: last_seen.get(&member.user.id.0).copied(),
+            })
+            .filter(|member| match member.last_seen {
+                Some(last_seen) => last_seen < cutoff,
+                None => true,
+            })
+            .collect();
+        //members that never wrote anything first, then the ones that were quiet the longest
+        inactive.sort_by_key(|member| (member.last_seen, member.id));
+
+        if inactive.is_empty() {
+            say_something(
+                format!("Every member wrote a message in the last {} days.", days),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+
+        let mut builder = Builder::default();
+        for member in inactive.iter().take(SHOWN_MEMBERS) {
+            builder.append(format!(
+                "<@!{}>: {}\n",
+                member.id,
+                format_last_seen(member.last_seen)
+            ));
+        }
+        if inactive.len() > SHOWN_MEMBERS {
+            builder.append(format!(
+                "...and {} more, see the attached file.",
+                inactive.len() - SHOWN_MEMBERS
+            ));
+        }
+        let description = builder.string().unwrap();
+        let title = format!(
+            "{} members without messages in the last {} days",
+            inactive.len(),
+            days
+        );
+        let csv = AttachmentType::Bytes {
+            data: Cow::from(to_csv(&inactive).into_bytes()),
+            filename: "inactive.csv".to_string(),
+        };
+        let _ = msg
+            .channel_id
+            .send_files(&ctx.http, vec![csv], |m| {
+                m.embed(|e| e.title(title).description(description));
+                m
+            })
+            .await;
+    }
+}
+
+fn format_last_seen(last_seen: Option<i64>) -> String {
+    match last_seen {
+        Some(timestamp) => Utc.timestamp(timestamp, 0).format("%Y-%m-%d").to_string(),
+        None => "never".to_string(),
+    }
+}
+
+fn to_csv(members: &[InactiveMember]) -> String {
+    let mut builder = Builder::default();
+    builder.append("user_id,name,last_activity\n");
+    for member in members {
+        builder.append(format!(
+            "{},\"{}\",{}\n",
+            member.id,
+            member.name.replace('"', "\"\""),
+            format_last_seen(member.last_seen)
+        ));
+    }
+    builder.string().unwrap()
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
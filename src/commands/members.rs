@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use serenity::client::Context;
+use serenity::model::guild::{Guild, Member};
+use serenity::model::id::{GuildId, RoleId, UserId};
+use serenity::model::user::User;
+
+use crate::features::cards::{CardData, MinecraftIdentity};
+use crate::features::links::{self, Link};
+use crate::{REDIS_CLIENT, VERIFIED_ROLE};
+
+//maximum page size of the member list endpoint
+const MEMBER_PAGE_SIZE: u64 = 1000;
+//roles verify hands out for Hypixel ranks
+pub const RANK_ROLES: [&str; 5] = ["MVP++", "MVP+", "MVP", "VIP+", "VIP"];
+
+///Fetches every member of the guild page by page since the cache may not contain all of them.
+pub async fn fetch_all_members(ctx: &Context, guild_id: GuildId) -> serenity::Result<Vec<Member>> {
+    let mut members: Vec<Member> = Vec::new();
+    let mut after: Option<UserId> = None;
+    loop {
+        let page = guild_id
+            .members(&ctx.http, Some(MEMBER_PAGE_SIZE), after)
+            .await?;
+        let full_page = page.len() as u64 == MEMBER_PAGE_SIZE;
+        after = page.last().map(|member| member.user.id);
+        members.extend(page);
+        if !full_page {
+            return Ok(members);
+        }
+    }
+}
+
+///Every current member of the guild, from the cache if it has all of them.
+pub async fn current_members(ctx: &Context, guild_id: GuildId) -> serenity::Result<Vec<Member>> {
+    let cached = guild_id
+        .to_guild_cached(&ctx)
+        .await
+        .filter(|guild| guild.members.len() as u64 >= guild.member_count)
+        .map(|guild| guild.members.into_values().collect());
+    match cached {
+        Some(members) => Ok(members),
+        None => fetch_all_members(ctx, guild_id).await,
+    }
+}
+
+///Resolves a role mention, a role id or the name of a role.
+pub fn find_role(guild: &Guild, input: &str) -> Option<RoleId> {
+    let id = input
+        .trim_start_matches("<@&")
+        .trim_end_matches('>')
+        .parse::<u64>()
+        .ok()
+        .map(RoleId);
+    if let Some(id) = id {
+        if guild.roles.contains_key(&id) {
+            return Some(id);
+        }
+    }
+    guild
+        .roles
+        .values()
+        .find(|role| role.name.eq_ignore_ascii_case(input))
+        .map(|role| role.id)
+}
+
+///The Minecraft account of a verified member, from the link stored when they verified.
+pub async fn verified_identity(ctx: &Context, member: &Member) -> Option<MinecraftIdentity> {
+    let guild = member.guild_id.to_guild_cached(&ctx).await?;
+    //without the link it isn't known whether the member verified after links were stored
+    let link = REDIS_CLIENT
+        .get_connection()
+        .and_then(|mut con| links::get_link(&mut con, member.guild_id.0, member.user.id.0))
+        .ok()?;
+    minecraft_identity(&guild, member, link.as_ref())
+}
+
+///Same as `verified_identity` with the guild and the link at hand, for resolving many members at once.
+///Members that verified before links were stored have none, verify set their nickname to their
+///Minecraft username and gave them the role of their rank back then.
+pub fn minecraft_identity(
+    guild: &Guild,
+    member: &Member,
+    link: Option<&Link>,
+) -> Option<MinecraftIdentity> {
+    if let Some(link) = link {
+        return Some(MinecraftIdentity {
+            username: link.username.clone(),
+            rank: link.rank.clone(),
+        });
+    }
+    let role_names: Vec<&str> = member
+        .roles
+        .iter()
+        .filter_map(|role| guild.roles.get(role))
+        .map(|role| role.name.as_str())
+        .collect();
+    if !role_names.contains(&VERIFIED_ROLE.as_str()) {
+        return None;
+    }
+    Some(MinecraftIdentity {
+        username: member.nick.clone()?,
+        rank: RANK_ROLES
+            .iter()
+            .find(|rank| role_names.contains(rank))
+            .map(|rank| rank.to_string()),
+    })
+}
+
+///Labels of every current member for lists that name many of them, members that left aren't in it.
+pub async fn member_labels(ctx: &Context, guild_id: GuildId) -> Option<HashMap<u64, String>> {
+    let guild = guild_id.to_guild_cached(&ctx).await?;
+    let members = current_members(ctx, guild_id).await.ok()?;
+    let links = REDIS_CLIENT
+        .get_connection()
+        .and_then(|mut con| links::guild_links(&mut con, guild_id.0))
+        .ok()?;
+    Some(
+        members
+            .iter()
+            .map(|member| {
+                let label = display_label(&guild, member, links.get(&member.user.id.0));
+                (member.user.id.0, label)
+            })
+            .collect(),
+    )
+}
+
+///Display name of the member, followed by their Minecraft username if they are verified under a different one.
+///Markdown in the names is escaped since labels end up in messages and embeds.
+pub fn display_label(guild: &Guild, member: &Member, link: Option<&Link>) -> String {
+    let name = member.display_name().to_string();
+    match minecraft_identity(guild, member, link) {
+        Some(minecraft) if minecraft.username != name => format!(
+            "{} ({})",
+            escape_markdown(&name),
+            escape_markdown(&minecraft.username)
+        ),
+        _ => escape_markdown(&name),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '~' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+///PNG version of the avatar of the user, the default avatar if they don't have one.
+pub fn avatar_url(user: &User) -> String {
+    match &user.avatar {
+        Some(hash) => format!(
+            "https://cdn.discordapp.com/avatars/{}/{}.png?size=128",
+            user.id, hash
+        ),
+        None => user.default_avatar_url(),
+    }
+}
+
+///Collects what a card shows about a member, members that left are shown as unknown.
+pub async fn card_data(
+    ctx: &Context,
+    guild_id: GuildId,
+    member_id: u64,
+    messages: u64,
+    rank: Option<u64>,
+) -> CardData {
+    match guild_id.member(&ctx, member_id).await {
+        Ok(member) => CardData {
+            member_id,
+            name: member.display_name().to_string(),
+            avatar_url: avatar_url(&member.user),
+            rank,
+            messages,
+            minecraft: verified_identity(ctx, &member).await,
+        },
+        Err(_) => CardData {
+            member_id,
+            name: "Unknown user".to_string(),
+            avatar_url: "https://cdn.discordapp.com/embed/avatars/0.png".to_string(),
+            rank,
+            messages,
+            minecraft: None,
+        },
+    }
+}
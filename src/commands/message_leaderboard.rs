@@ -1,15 +1,23 @@
-use std::cmp::Reverse;
+use std::borrow::Cow;
 use std::collections::HashMap;
 
+use redis::{Connection, RedisResult};
 use serenity::async_trait;
 use serenity::client::Context;
+use serenity::http::AttachmentType;
 use serenity::model::channel::Message;
-use string_builder::Builder;
 
 use crate::commands::command::Command;
+use crate::commands::members::{card_data, current_members, display_label, find_role};
+use crate::commands::pagination::{page_count, send_paginated, PAGE_SIZE};
+use crate::features::periods::{period_key, Period};
+use crate::features::{cards, leaderboard, links, seasons};
 use crate::say_something;
 use crate::REDIS_CLIENT;
 
+//id of the filter, a role id or `leaderboard::EVERYONE_FILTER`, and the members that are ranked
+type MemberFilter = (u64, Vec<u64>);
+
 pub struct CommandArgs {
     pub prefix: String,
     pub command: String,
@@ -24,33 +32,134 @@ impl Command for CommandArgs {
         }
         let ctx = ctx.clone();
         let msg = msg.clone();
-        //read contents of the file
-        let messages = get_messages(*msg.guild_id.unwrap().as_u64()).await;
-        if messages.is_none() {
+        //optional period or past season and page to start on, defaults to all time and the first page
+        let mut period = Period::AllTime;
+        let mut season = None;
+        let mut page = 1;
+        let mut image = false;
+        //members that left are hidden unless asked for
+        let mut departed = false;
+        let (content, role_arg) = split_role_arg(&msg.content);
+        let mut args = content.split_whitespace().skip(1).peekable();
+        while let Some(arg) = args.next() {
+            if arg == "image" {
+                image = true;
+            } else if arg == "departed" {
+                departed = true;
+            } else if arg == "season" && args.peek().and_then(|n| n.parse::<u64>().ok()).is_some() {
+                season = args.next().and_then(|n| n.parse::<u64>().ok());
+            } else if let Some(parsed) = Period::from_arg(arg) {
+                period = parsed;
+            } else if let Ok(parsed) = arg.parse::<usize>() {
+                page = parsed;
+            } else {
+                say_something(
+                    format!(
+                        "Invalid usage: `{} [week|month|season|all|season <number>] [page] [image] [departed] [role:<role>]`",
+                        command
+                    ),
+                    ctx,
+                    msg,
+                )
+                .await;
+                return;
+            }
+        }
+
+        let guild_id = msg.guild_id.unwrap();
+        let guild = guild_id.to_guild_cached(&ctx).await;
+        let members = current_members(&ctx, guild_id).await;
+        let links = REDIS_CLIENT
+            .get_connection()
+            .and_then(|mut con| links::guild_links(&mut con, guild_id.0));
+        if guild.is_none() || members.is_err() || links.is_err() {
             send_err(ctx, msg).await;
             return;
         }
-        let messages = messages.unwrap();
-        //sort messages in Vector
-        let mut sorted: Vec<_> = messages.iter().collect();
-        sorted.sort_by_key(|w| Reverse(*w));
+        let guild = guild.unwrap();
+        let members = members.unwrap();
+        let links = links.unwrap();
+        //names are resolved up front since the pages are rendered without the cache
+        let names: HashMap<u64, String> = members
+            .iter()
+            .map(|member| {
+                let label = display_label(&guild, member, links.get(&member.user.id.0));
+                (member.user.id.0, label)
+            })
+            .collect();
 
-        //assemble final message
-        let mut builder = Builder::default();
-        let mut current_position: u8 = 1;
-        for (key, value) in sorted.iter() {
-            //discord mention
-            let output = format!(
-                "<@!{}> has {} messages and is Place {}\n",
-                value, key, current_position
-            );
-            builder.append(output);
-            if current_position == 10 {
-                break;
+        //only members with the role or everyone that is still on the server are ranked
+        let mut role_name = None;
+        let mut filter = None;
+        if let Some(role_arg) = role_arg {
+            let role_id = find_role(&guild, &role_arg);
+            if role_id.is_none() {
+                say_something(format!("Unknown role `{}`", role_arg), ctx, msg).await;
+                return;
+            }
+            let role_id = role_id.unwrap();
+            role_name = guild.roles.get(&role_id).map(|role| role.name.clone());
+            let with_role = members
+                .iter()
+                .filter(|member| member.roles.contains(&role_id))
+                .map(|member| member.user.id.0)
+                .collect();
+            filter = Some((role_id.0, with_role));
+        } else if !departed {
+            filter = Some((
+                leaderboard::EVERYONE_FILTER,
+                members.iter().map(|member| member.user.id.0).collect(),
+            ));
+        }
+
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() {
+            println!("Error while opening redis connection");
+            send_err(ctx, msg).await;
+            return;
+        }
+        let mut con = con.unwrap();
+        let guild_id = guild_id.0;
+        let title = match season {
+            Some(number) => {
+                let archived = seasons::archived_season(&mut con, guild_id, number);
+                if archived.is_err() {
+                    send_err(ctx, msg).await;
+                    return;
+                }
+                match archived.unwrap() {
+                    Some(archived) => format!(
+                        "Message leaderboard of season {} ({})",
+                        number,
+                        archived.describe()
+                    ),
+                    None => {
+                        say_something(format!("Season {} hasn't ended yet.", number), ctx, msg)
+                            .await;
+                        return;
+                    }
+                }
             }
-            current_position += 1;
+            None => format!("Current message leaderboard ({})", period.name()),
+        };
+        let title = match role_name {
+            Some(role_name) => format!("{}, {} only", title, role_name),
+            None => title,
+        };
+        let key = leaderboard_key(&mut con, guild_id, period, season, &filter);
+        if key.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let key = key.unwrap();
+        let members = leaderboard::member_count(&mut con, &key);
+        if members.is_err() {
+            println!("err {:?}", members.err());
+            send_err(ctx, msg).await;
+            return;
         }
-        if builder.len() == 0 {
+        let members = members.unwrap();
+        if members == 0 {
             say_something(
                 "There are currently no messages stored for this Server.".to_string(),
                 ctx,
@@ -59,53 +168,128 @@ impl Command for CommandArgs {
             .await;
             return;
         }
-        let message = builder.string().unwrap();
-        let _ = msg
-            .channel_id
-            .send_message(&ctx.http, |m| {
-                m.embed(|e| e.title("Current message leaderboard").description(message));
-                m
-            })
-            .await;
+
+        if image {
+            send_image(ctx, msg, &key, page, page_count(members)).await;
+            return;
+        }
+
+        //only the page that is shown gets loaded
+        let render = move |page: usize| {
+            let mut con = REDIS_CLIENT.get_connection().ok()?;
+            //combined periods expire, so the key is looked up again for every page
+            let key = leaderboard_key(&mut con, guild_id, period, season, &filter).ok()?;
+            let entries = leaderboard::range(&mut con, &key, (page - 1) * PAGE_SIZE, PAGE_SIZE);
+            let lines = entries
+                .ok()?
+                .iter()
+                .map(|entry| {
+                    let name = match names.get(&entry.member_id) {
+                        Some(name) => name.as_str(),
+                        None => "Unknown user",
+                    };
+                    format!(
+                        "{} has {} messages and is Place {}",
+                        name, entry.messages, entry.rank
+                    )
+                })
+                .collect();
+            Some(lines)
+        };
+        send_paginated(ctx, msg, title, page_count(members), page, render).await;
     }
 }
 
-async fn send_err(ctx: Context, msg: Message) {
-    say_something(
-        "An internal Error occurred while processing this command.".to_string(),
-        ctx,
-        msg,
+fn leaderboard_key(
+    con: &mut Connection,
+    guild_id: u64,
+    period: Period,
+    season: Option<u64>,
+    filter: &Option<MemberFilter>,
+) -> RedisResult<String> {
+    let key = match season {
+        Some(number) => seasons::archive_key(guild_id, number),
+        None => period_key(con, guild_id, period)?,
+    };
+    match filter {
+        Some((filter_id, members)) => leaderboard::filtered(con, &key, *filter_id, members),
+        None => Ok(key),
+    }
+}
+
+///Takes `role:<role>` out of the message. A role mention can be followed by other arguments,
+///a role name takes up the rest of the message since it can contain spaces.
+fn split_role_arg(content: &str) -> (String, Option<String>) {
+    let index = match content.find("role:") {
+        Some(index) => index,
+        None => return (content.to_string(), None),
+    };
+    let role = content[index + "role:".len()..].trim_start();
+    if role.starts_with("<@&") {
+        let end = role.find(char::is_whitespace).unwrap_or(role.len());
+        let rest = format!("{} {}", &content[..index], &role[end..]);
+        return (rest, Some(role[..end].to_string()));
+    }
+    (
+        content[..index].to_string(),
+        Some(role.trim_end().to_string()),
     )
-    .await;
 }
 
-async fn get_messages(guild_id: u64) -> Option<HashMap<u64, String>> {
-    let con = REDIS_CLIENT.get_connection();
-    if con.is_err() {
-        println!("Error while opening redis connection");
-        return None;
+///Replies with a rendered card of one page instead of the embed.
+async fn send_image(ctx: Context, msg: Message, key: &str, page: usize, pages: usize) {
+    if page == 0 || page > pages {
+        say_something(
+            format!("Invalid page, there are only {} pages.", pages),
+            ctx,
+            msg,
+        )
+        .await;
+        return;
     }
-    let mut con = con.unwrap();
-    let key = format!("verifybot:messages:{}", guild_id);
-    let result = redis::cmd("HGETALL").arg(&key).clone().iter(&mut con);
-    if result.is_err() {
-        println!("err {:?}", result.err());
-        return None;
+    let entries = REDIS_CLIENT
+        .get_connection()
+        .and_then(|mut con| leaderboard::range(&mut con, key, (page - 1) * PAGE_SIZE, PAGE_SIZE));
+    if entries.is_err() {
+        send_err(ctx, msg).await;
+        return;
     }
-
-    let result: redis::Iter<String> = result.unwrap();
-    let mut messages: HashMap<u64, String> = HashMap::new();
-    let mut key = true;
-    let mut current_key: String = "".to_string();
-    for x in result {
-        if key {
-            current_key = x;
-            key = false;
-        } else {
-            messages.insert(x.parse().unwrap(), current_key.clone());
-            key = true;
-        }
+    let guild_id = msg.guild_id.unwrap();
+    let mut rows = Vec::new();
+    for entry in entries.unwrap() {
+        rows.push(
+            card_data(
+                &ctx,
+                guild_id,
+                entry.member_id,
+                entry.messages,
+                Some(entry.rank),
+            )
+            .await,
+        );
+    }
+    let card = cards::leaderboard_card(guild_id.0, &rows).await;
+    if card.is_none() {
+        send_err(ctx, msg).await;
+        return;
     }
+    let file = AttachmentType::Bytes {
+        data: Cow::from(card.unwrap()),
+        filename: "leaderboard.png".to_string(),
+    };
+    let _ = msg
+        .channel_id
+        .send_files(&ctx.http, vec![file], |m| {
+            m.content(format!("Page {}/{}", page, pages))
+        })
+        .await;
+}
 
-    Some(messages)
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
 }
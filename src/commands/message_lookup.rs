@@ -1,13 +1,24 @@
-use redis::Commands;
-use redis::ErrorKind;
-use redis::RedisResult;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
 use serenity::async_trait;
 
 use crate::commands::command::Command;
+use crate::commands::members::{card_data, member_labels};
+use crate::commands::resolve::resolve_member_arg;
+use crate::features::cards;
+use crate::features::leaderboard::Standing;
+use crate::features::periods::Period;
+use crate::features::{member_stats, voice_tracking};
 use crate::say_something;
 use crate::REDIS_CLIENT;
 use serenity::client::Context;
+use serenity::http::AttachmentType;
 use serenity::model::channel::Message;
+use string_builder::Builder;
+
+//entries shown above and below the member
+const AROUND: usize = 2;
 
 pub struct CommandArgs {
     pub prefix: String,
@@ -24,58 +35,139 @@ impl Command for CommandArgs {
         let ctx = ctx.clone();
         let msg = msg.clone();
 
-        let user_id: String;
-
-        let split: Vec<&str> = msg.content.split(' ').collect();
-        if split.len() == 2 {
-            let user = split[1];
-            //Message contains "&" if a role was mentioned
-            if user.contains('&') {
-                say_something("Invalid User".to_string(), ctx, msg).await;
-                return;
+        let mut split: Vec<&str> = msg.content.split_whitespace().collect();
+        //reply with a rendered card instead of the embed
+        let image = split.contains(&"image");
+        split.retain(|arg| *arg != "image");
+        //everything after the command is the member, names can contain spaces
+        let member_id = if split.len() > 1 {
+            match resolve_member_arg(&ctx, &msg, &split[1..].join(" ")).await {
+                Some(member_id) => member_id,
+                None => return,
             }
-            user_id = user.replace("<@!", "").replace(">", "");
         } else {
-            user_id = msg.author.id.to_string();
-        }
+            msg.author.id.0
+        };
 
-        let guild_id = msg.guild_id.unwrap().to_string();
-        let key = format!("verifybot:messages:{}", guild_id);
         //make query
+        let labels = member_labels(&ctx, msg.guild_id.unwrap()).await;
         let con = REDIS_CLIENT.get_connection();
-        if con.is_err() {
+        if con.is_err() || labels.is_none() {
             send_err(ctx, msg).await;
             return;
         }
         let mut con = con.unwrap();
-        let result: RedisResult<isize> = con.hget(key, &user_id);
-        let current_messages: isize;
-        if result.is_err() {
-            if !(result.err().unwrap().kind() == ErrorKind::TypeError) {
+        let labels = labels.unwrap();
+        let members: Vec<u64> = labels.keys().copied().collect();
+        //where the member stands all time and in the current period
+        let stats = member_stats::read(
+            &mut con,
+            msg.guild_id.unwrap().0,
+            member_id,
+            &[Period::AllTime, Period::Month],
+            AROUND,
+            &members,
+        );
+        if stats.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let stats = stats.unwrap();
+        let rank = stats.standings[0].1.as_ref().map(|standing| standing.rank);
+
+        if image {
+            let guild_id = msg.guild_id.unwrap();
+            let data = card_data(&ctx, guild_id, member_id, stats.messages, rank).await;
+            let card = cards::profile_card(guild_id.0, &data).await;
+            if card.is_none() {
                 send_err(ctx, msg).await;
                 return;
-            } else {
-                current_messages = 0;
             }
-        } else {
-            current_messages = result.unwrap();
+            let file = AttachmentType::Bytes {
+                data: Cow::from(card.unwrap()),
+                filename: "profile.png".to_string(),
+            };
+            let _ = msg
+                .channel_id
+                .send_files(&ctx.http, vec![file], |m| m)
+                .await;
+            return;
         }
 
         let message = format!(
-            "<@!{}> currently has {} messages.",
-            user_id, current_messages
+            "{} currently has {} messages.\nStreak: {} days (longest: {} days)\nVoice: {}",
+            label(&labels, member_id),
+            stats.messages,
+            stats.streak.current,
+            stats.streak.longest,
+            voice_tracking::format_duration(stats.voice)
         );
         //send embed
         let _ = msg
             .channel_id
             .send_message(&ctx.http, |m| {
-                m.embed(|e| e.title("Message lookup").description(message));
+                m.embed(|e| {
+                    e.title("Message lookup").description(message);
+                    for (period, standing) in stats.standings {
+                        e.field(
+                            period.name(),
+                            format_standing(standing, member_id, &labels),
+                            false,
+                        );
+                    }
+                    e
+                });
                 m
             })
             .await;
     }
 }
 
+fn format_standing(
+    standing: Option<Standing>,
+    member_id: u64,
+    labels: &HashMap<u64, String>,
+) -> String {
+    let standing = match standing {
+        Some(standing) => standing,
+        None => return "No messages yet".to_string(),
+    };
+    let mut builder = Builder::default();
+    match standing.gap_above {
+        Some(gap) => builder.append(format!(
+            "Place {} with {} messages, {} behind the next place\n",
+            standing.rank, standing.messages, gap
+        )),
+        None => builder.append(format!(
+            "Place {} with {} messages\n",
+            standing.rank, standing.messages
+        )),
+    }
+    for entry in standing.around {
+        let line = format!(
+            "{}. {}: {}",
+            entry.rank,
+            label(labels, entry.member_id),
+            entry.messages
+        );
+        //highlight the member that was looked up
+        if entry.member_id == member_id {
+            builder.append(format!("**{}**\n", line));
+        } else {
+            builder.append(format!("{}\n", line));
+        }
+    }
+    builder.string().unwrap()
+}
+
+///Members that left are only in the ranking of a member that left too.
+fn label(labels: &HashMap<u64, String>, member_id: u64) -> &str {
+    match labels.get(&member_id) {
+        Some(label) => label.as_str(),
+        None => "Unknown user",
+    }
+}
+
 async fn send_err(ctx: Context, msg: Message) {
     say_something(
         "An internal Error occurred while processing this command.".to_string(),
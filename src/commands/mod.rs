@@ -3,3 +3,20 @@ pub mod message_leaderboard;
 pub mod message_lookup;
 pub mod verify_command;
 pub mod set_guild;
+pub mod permissions;
+pub mod backfill;
+pub mod stats;
+pub mod streaks;
+pub mod members;
+pub mod inactive;
+pub mod voice_leaderboard;
+pub mod pagination;
+pub mod schedule;
+pub mod set_timezone;
+pub mod season;
+pub mod profile;
+pub mod compare;
+pub mod resolve;
+pub mod api_cache;
+pub mod resync;
+pub mod hypixel_queue;
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use serenity::client::Context;
+use serenity::collector::ReactionAction;
+use serenity::model::channel::{Message, ReactionType};
+
+use crate::say_something;
+
+//lines shown on every page
+pub const PAGE_SIZE: usize = 10;
+//the page can't be changed anymore after this much time without a reaction
+const PAGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+const FIRST: &str = "⏮";
+const PREVIOUS: &str = "◀";
+const NEXT: &str = "▶";
+const LAST: &str = "⏭";
+
+///Number of pages needed for `lines` lines.
+pub fn page_count(lines: usize) -> usize {
+    lines.div_ceil(PAGE_SIZE).max(1)
+}
+
+///Sends an embed with one of `pages` pages at a time, starting on `page` (1 based).
+///`render` builds the lines of a page when it is shown, so only the visible page has to be loaded.
+///Only the author of `msg` can flip pages by adding or removing one of the navigation reactions.
+pub async fn send_paginated<F>(
+    ctx: Context,
+    msg: Message,
+    title: String,
+    pages: usize,
+    page: usize,
+    render: F,
+) where
+    F: Fn(usize) -> Option<Vec<String>> + Send + Sync,
+{
+    if page == 0 || page > pages {
+        say_something(
+            format!("Invalid page, there are only {} pages.", pages),
+            ctx,
+            msg,
+        )
+        .await;
+        return;
+    }
+    let mut page = page;
+    let description = match render(page) {
+        Some(lines) => lines.join("\n"),
+        None => {
+            say_something(
+                "An internal Error occurred while processing this command.".to_string(),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+    };
+    let sent = msg
+        .channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(&title)
+                    .description(description)
+                    .footer(|f| f.text(format!("Page {}/{}", page, pages)))
+            });
+            m
+        })
+        .await;
+    let mut sent = match sent {
+        Ok(sent) => sent,
+        Err(_) => return,
+    };
+    if pages == 1 {
+        return;
+    }
+    for emoji in &[FIRST, PREVIOUS, NEXT, LAST] {
+        if sent
+            .react(&ctx, ReactionType::Unicode(emoji.to_string()))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+
+    //adding and removing a reaction both count as a click, so the bot doesn't need to remove reactions
+    while let Some(action) = sent
+        .await_reaction(&ctx)
+        .author_id(msg.author.id)
+        .removed(true)
+        .timeout(PAGE_TIMEOUT)
+        .await
+    {
+        let reaction = match action.as_ref() {
+            ReactionAction::Added(reaction) | ReactionAction::Removed(reaction) => reaction,
+        };
+        let new_page = match &reaction.emoji {
+            ReactionType::Unicode(emoji) if emoji == FIRST => 1,
+            ReactionType::Unicode(emoji) if emoji == PREVIOUS => page.saturating_sub(1).max(1),
+            ReactionType::Unicode(emoji) if emoji == NEXT => (page + 1).min(pages),
+            ReactionType::Unicode(emoji) if emoji == LAST => pages,
+            _ => continue,
+        };
+        if new_page == page {
+            continue;
+        }
+        let description = match render(new_page) {
+            Some(lines) => lines.join("\n"),
+            None => continue,
+        };
+        page = new_page;
+        let _ = sent
+            .edit(&ctx, |m| {
+                m.embed(|e| {
+                    e.title(&title)
+                        .description(description)
+                        .footer(|f| f.text(format!("Page {}/{}", page, pages)))
+                })
+            })
+            .await;
+    }
+    //show that the buttons stopped working
+    let _ = sent.delete_reactions(&ctx).await;
+}
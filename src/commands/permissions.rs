@@ -0,0 +1,44 @@
+use serenity::client::Context;
+use serenity::model::channel::Message;
+
+use crate::say_something;
+
+///Returns true if the author of the message has the "Manage Guild" permission, otherwise tells them why the command was refused.
+pub async fn check_manage_guild(ctx: &Context, msg: &Message) -> bool {
+    let guild = msg.guild(&ctx).await;
+    if guild.is_none() {
+        send_err(ctx, msg).await;
+        return false;
+    }
+
+    let member = guild.unwrap().member(&ctx.http, msg.author.id).await;
+    if member.is_err() {
+        send_err(ctx, msg).await;
+        return false;
+    }
+    let perms = member.unwrap().permissions(&ctx).await;
+    if perms.is_err() {
+        send_err(ctx, msg).await;
+        return false;
+    }
+    //check for correct perms
+    if !perms.unwrap().manage_guild() {
+        say_something(
+            "You need the \"Manage Guild\" Permission to use this command".to_string(),
+            ctx.clone(),
+            msg.clone(),
+        )
+        .await;
+        return false;
+    }
+    true
+}
+
+async fn send_err(ctx: &Context, msg: &Message) {
+    say_something(
+        "An Error occured while executing this command.".to_string(),
+        ctx.clone(),
+        msg.clone(),
+    )
+    .await;
+}
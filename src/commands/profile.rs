@@ -0,0 +1,183 @@
+use redis::Commands;
+use redis::RedisResult;
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+
+use crate::commands::command::Command;
+use crate::commands::hypixel_queue::announce_position;
+use crate::commands::members::{avatar_url, current_members, minecraft_identity};
+use crate::commands::resolve::resolve_member_arg;
+use crate::features::cards::head_url;
+use crate::features::leaderboard;
+use crate::features::levels::level_for;
+use crate::features::links;
+use crate::features::message_counting::messages_key;
+use crate::features::streaks;
+use crate::hypixel::cache::Endpoint;
+use crate::say_something;
+use crate::{HYPIXEL_CLIENT, REDIS_CLIENT};
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+
+        let split: Vec<&str> = msg.content.split_whitespace().collect();
+        let member_id = if split.len() > 1 {
+            match resolve_member_arg(&ctx, &msg, &split[1..].join(" ")).await {
+                Some(member_id) => member_id,
+                None => return,
+            }
+        } else {
+            msg.author.id.0
+        };
+        let guild_id = msg.guild_id.unwrap();
+        let member = guild_id.member(&ctx, member_id).await;
+        let guild = guild_id.to_guild_cached(&ctx).await;
+        if member.is_err() || guild.is_none() {
+            say_something("Invalid User".to_string(), ctx, msg).await;
+            return;
+        }
+        let member = member.unwrap();
+
+        let members = current_members(&ctx, guild_id).await;
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() || members.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let mut con = con.unwrap();
+        let messages: RedisResult<Option<u64>> = con.zscore(messages_key(guild_id.0), member_id);
+        let members: Vec<u64> = members
+            .unwrap()
+            .iter()
+            .map(|member| member.user.id.0)
+            .collect();
+        //ranked like on the leaderboard, which hides members that left
+        let standing =
+            leaderboard::ranking_for(&mut con, &messages_key(guild_id.0), member_id, &members)
+                .and_then(|key| leaderboard::standing(&mut con, &key, member_id, 0));
+        let streak = streaks::get_streak(&mut con, guild_id.0, member_id);
+        //members that verified before links were stored have none, those fall back to a Mojang lookup
+        let link = links::get_link(&mut con, guild_id.0, member_id);
+        let global_link = links::global_link(&mut con, member_id);
+        if messages.is_err()
+            || standing.is_err()
+            || streak.is_err()
+            || link.is_err()
+            || global_link.is_err()
+        {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let messages = messages.unwrap().unwrap_or(0);
+        let rank = match standing.unwrap() {
+            Some(standing) => format!("#{}", standing.rank),
+            None => "Unranked".to_string(),
+        };
+        let level = level_for(messages);
+        let streak = streak.unwrap();
+        let link = link.unwrap();
+        let global_link = global_link.unwrap();
+        let joined = match member.joined_at {
+            Some(joined) => joined.format("%Y-%m-%d").to_string(),
+            None => "Unknown".to_string(),
+        };
+
+        //the Minecraft part is only known for verified members
+        let mut minecraft_fields = Vec::new();
+        let thumbnail = match minecraft_identity(&guild.unwrap(), &member, link.as_ref()) {
+            Some(minecraft) => {
+                //a link from another guild can be a different account, it only counts if the username matches
+                let link = link.or_else(|| {
+                    global_link
+                        .filter(|link| link.username.eq_ignore_ascii_case(&minecraft.username))
+                });
+                let uuid = match &link {
+                    Some(link) => Some(link.uuid.clone()),
+                    None => HYPIXEL_CLIENT
+                        .profile(&minecraft.username)
+                        .await
+                        .ok()
+                        .map(|profile| profile.id),
+                };
+                if let Some(uuid) = &uuid {
+                    announce_position(&ctx, &msg, uuid, &[Endpoint::Guild], "profile").await;
+                }
+                let hypixel_guild = match &uuid {
+                    Some(uuid) => HYPIXEL_CLIENT
+                        .guild_of(uuid, guild_id.0)
+                        .await
+                        .ok()
+                        .flatten(),
+                    None => None,
+                };
+                minecraft_fields.push(("Minecraft", minecraft.username.clone()));
+                minecraft_fields.push(("UUID", uuid.unwrap_or_else(|| "Unknown".to_string())));
+                minecraft_fields.push((
+                    "Hypixel rank",
+                    minecraft.rank.unwrap_or_else(|| "None".to_string()),
+                ));
+                minecraft_fields.push((
+                    "Hypixel guild",
+                    hypixel_guild.map_or_else(|| "None".to_string(), |guild| guild.name),
+                ));
+                if let Some(link) = &link {
+                    minecraft_fields.push(("Verified", link.verified_date()));
+                }
+                head_url(&minecraft.username)
+            }
+            None => {
+                minecraft_fields.push(("Minecraft", "Not verified".to_string()));
+                avatar_url(&member.user)
+            }
+        };
+
+        let _ = msg
+            .channel_id
+            .send_message(&ctx.http, |m| {
+                m.embed(|e| {
+                    e.title(format!("Profile of {}", member.display_name()))
+                        .thumbnail(thumbnail)
+                        .field("Messages", messages, true)
+                        .field("Rank", rank, true)
+                        .field(
+                            "Level",
+                            format!("{} ({}/{})", level.level, level.progress, level.needed),
+                            true,
+                        )
+                        .field(
+                            "Streak",
+                            format!("{} days (longest: {} days)", streak.current, streak.longest),
+                            true,
+                        )
+                        .field("Joined", joined, true);
+                    for (name, value) in minecraft_fields {
+                        e.field(name, value, true);
+                    }
+                    e
+                })
+            })
+            .await;
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use redis::Commands;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::model::guild::{Guild, Member};
+
+use crate::commands::members::{current_members, display_label, minecraft_identity};
+use crate::features::links::{self, Link};
+use crate::features::message_counting::messages_key;
+use crate::{say_something, REDIS_CLIENT};
+
+//suggestions shown when the input matches several members
+const MAX_SUGGESTIONS: usize = 5;
+//digits an id may be off by to be suggested
+const MAX_ID_TYPOS: usize = 2;
+
+pub enum Resolved {
+    Found(u64),
+    ///Several members could be meant, with their labels.
+    Ambiguous(Vec<(u64, String)>),
+    ///Nothing matches, but these members have similar names, closest first.
+    Similar(Vec<(u64, String)>),
+    NotFound,
+}
+
+///Resolves a mention, a user id, `name#1234`, a username, a nickname or a verified Minecraft username.
+///Partial names are matched if only one member contains them, otherwise similar names are suggested.
+///None if Discord or redis couldn't be reached.
+pub async fn resolve_member(ctx: &Context, guild: &Guild, input: &str) -> Option<Resolved> {
+    //mentions look like <@id> or <@!id>, role mentions <@&id>
+    let mention = input
+        .strip_prefix("<@")
+        .and_then(|mention| mention.strip_suffix('>'))
+        .map(|mention| mention.trim_start_matches('!'));
+    if let Some(id) = mention.and_then(|id| id.parse::<u64>().ok()) {
+        return Some(Resolved::Found(id));
+    }
+
+    let members = current_members(ctx, guild.id).await.ok()?;
+    let mut con = REDIS_CLIENT.get_connection().ok()?;
+    let links = links::guild_links(&mut con, guild.id.0).ok()?;
+    //members that left still have their counts, other numbers can be names or mistyped ids
+    if let Ok(id) = input.parse::<u64>() {
+        let counted: Option<u64> = con.zscore(messages_key(guild.id.0), id).ok()?;
+        if counted.is_some() || members.iter().any(|member| member.user.id.0 == id) {
+            return Some(Resolved::Found(id));
+        }
+    }
+
+    let names: Vec<(u64, Vec<String>)> = members
+        .iter()
+        .map(|member| (member.user.id.0, names_of(guild, member, &links)))
+        .collect();
+    let member = |member_id: u64| {
+        members
+            .iter()
+            .find(|member| member.user.id.0 == member_id)
+            .unwrap()
+    };
+    let mut matched = find_matches(&input.to_lowercase(), &names);
+    if matches!(&matched, NameMatch::Similar(ids) if ids.is_empty()) && input.parse::<u64>().is_ok()
+    {
+        let ids: Vec<u64> = members.iter().map(|member| member.user.id.0).collect();
+        matched = NameMatch::Similar(similar_ids(input, &ids));
+    }
+    Some(match matched {
+        NameMatch::Exact(ids) | NameMatch::Partial(ids) if ids.len() == 1 => {
+            Resolved::Found(ids[0])
+        }
+        NameMatch::Similar(ids) if ids.is_empty() => Resolved::NotFound,
+        NameMatch::Exact(ids) | NameMatch::Partial(ids) => {
+            Resolved::Ambiguous(suggestions(guild, &links, ids.into_iter().map(member)))
+        }
+        NameMatch::Similar(ids) => {
+            Resolved::Similar(suggestions(guild, &links, ids.into_iter().map(member)))
+        }
+    })
+}
+
+enum NameMatch {
+    Exact(Vec<u64>),
+    Partial(Vec<u64>),
+    ///Ordered from the closest name on, empty if nothing is close.
+    Similar(Vec<u64>),
+}
+
+///Matches the lowercase input against the lowercase names of each member,
+///exact matches win over partial ones, which win over similar ones.
+fn find_matches(input: &str, names: &[(u64, Vec<String>)]) -> NameMatch {
+    let exact: Vec<u64> = names
+        .iter()
+        .filter(|(_, names)| names.iter().any(|name| name == input))
+        .map(|(member_id, _)| *member_id)
+        .collect();
+    if !exact.is_empty() {
+        return NameMatch::Exact(exact);
+    }
+    let partial: Vec<u64> = names
+        .iter()
+        .filter(|(_, names)| names.iter().any(|name| name.contains(input)))
+        .map(|(member_id, _)| *member_id)
+        .collect();
+    if !partial.is_empty() {
+        return NameMatch::Partial(partial);
+    }
+
+    //typos, names within a third of their length in edits are suggested
+    let max_distance = (input.chars().count() / 3).max(1);
+    let mut similar: Vec<(usize, u64)> = names
+        .iter()
+        .filter_map(|(member_id, names)| {
+            let distance = names.iter().map(|name| distance(name, input)).min()?;
+            if distance <= max_distance {
+                Some((distance, *member_id))
+            } else {
+                None
+            }
+        })
+        .collect();
+    //stable, so equally close members keep their order
+    similar.sort_by_key(|(distance, _)| *distance);
+    NameMatch::Similar(
+        similar
+            .into_iter()
+            .map(|(_, member_id)| member_id)
+            .collect(),
+    )
+}
+
+///Ids that are a few typos away from the input, closest first.
+fn similar_ids(input: &str, ids: &[u64]) -> Vec<u64> {
+    let mut similar: Vec<(usize, u64)> = ids
+        .iter()
+        .map(|id| (distance(&id.to_string(), input), *id))
+        .filter(|(distance, _)| *distance <= MAX_ID_TYPOS)
+        .collect();
+    similar.sort_by_key(|(distance, _)| *distance);
+    similar.into_iter().map(|(_, id)| id).collect()
+}
+
+///Resolves the argument and replies if that isn't possible, for commands that target a member.
+pub async fn resolve_member_arg(ctx: &Context, msg: &Message, input: &str) -> Option<u64> {
+    let guild = msg.guild(&ctx).await;
+    let resolved = match &guild {
+        Some(guild) => resolve_member(ctx, guild, input).await,
+        None => None,
+    };
+    let reply = match resolved {
+        Some(Resolved::Found(member_id)) => return Some(member_id),
+        Some(Resolved::Ambiguous(candidates)) => format!(
+            "Multiple members match `{}`, did you mean {}?",
+            input,
+            labels(&candidates)
+        ),
+        Some(Resolved::Similar(candidates)) => format!(
+            "No member matches `{}`, did you mean {}?",
+            input,
+            labels(&candidates)
+        ),
+        Some(Resolved::NotFound) => format!("No member matches `{}`.", input),
+        None => "An internal Error occurred while processing this command.".to_string(),
+    };
+    say_something(reply, ctx.clone(), msg.clone()).await;
+    None
+}
+
+fn labels(candidates: &[(u64, String)]) -> String {
+    let labels: Vec<String> = candidates
+        .iter()
+        .map(|(member_id, label)| format!("{} (`{}`)", label, member_id))
+        .collect();
+    labels.join(", ")
+}
+
+///Lowercase names the member can be found by.
+fn names_of(guild: &Guild, member: &Member, links: &HashMap<u64, Link>) -> Vec<String> {
+    let mut names = vec![
+        member.user.name.to_lowercase(),
+        member.user.tag().to_lowercase(),
+    ];
+    if let Some(nick) = &member.nick {
+        names.push(nick.to_lowercase());
+    }
+    if let Some(minecraft) = minecraft_identity(guild, member, links.get(&member.user.id.0)) {
+        names.push(minecraft.username.to_lowercase());
+    }
+    names
+}
+
+fn suggestions<'a>(
+    guild: &Guild,
+    links: &HashMap<u64, Link>,
+    members: impl Iterator<Item = &'a Member>,
+) -> Vec<(u64, String)> {
+    members
+        .take(MAX_SUGGESTIONS)
+        .map(|member| {
+            let label = display_label(guild, member, links.get(&member.user.id.0));
+            (member.user.id.0, label)
+        })
+        .collect()
+}
+
+///Levenshtein distance, the number of single character edits between the two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(members: &[(u64, &[&str])]) -> Vec<(u64, Vec<String>)> {
+        members
+            .iter()
+            .map(|(member_id, names)| {
+                (
+                    *member_id,
+                    names.iter().map(|name| name.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn ids(matched: NameMatch) -> (&'static str, Vec<u64>) {
+        match matched {
+            NameMatch::Exact(ids) => ("exact", ids),
+            NameMatch::Partial(ids) => ("partial", ids),
+            NameMatch::Similar(ids) => ("similar", ids),
+        }
+    }
+
+    #[test]
+    fn distance_counts_single_character_edits() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("steve", "steve"), 0);
+        assert_eq!(distance("steve", "stve"), 1);
+        assert_eq!(distance("steve", "steven"), 1);
+        assert_eq!(distance("steve", "stave"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        //characters, not bytes
+        assert_eq!(distance("jörg", "jorg"), 1);
+    }
+
+    #[test]
+    fn exact_matches_win_over_partial_ones() {
+        let names = names(&[(1, &["steve", "steve#0001"]), (2, &["stevenson"])]);
+        assert_eq!(ids(find_matches("steve", &names)), ("exact", vec![1]));
+        assert_eq!(ids(find_matches("teve", &names)), ("partial", vec![1, 2]));
+        assert_eq!(ids(find_matches("nson", &names)), ("partial", vec![2]));
+    }
+
+    #[test]
+    fn mistyped_ids_suggest_the_closest_ids() {
+        let ids = [246813579024681357, 246813579024681300, 111111111111111111];
+        assert_eq!(
+            similar_ids("246813579024681375", &ids),
+            vec![246813579024681357, 246813579024681300]
+        );
+        assert_eq!(
+            similar_ids("246813579024681357", &ids)[0],
+            246813579024681357
+        );
+        assert!(similar_ids("999999999999999999", &ids).is_empty());
+    }
+
+    #[test]
+    fn numbers_match_names_made_of_digits() {
+        let names = names(&[(246813579024681357, &["1337"]), (2, &["13370"])]);
+        assert_eq!(
+            ids(find_matches("1337", &names)),
+            ("exact", vec![246813579024681357])
+        );
+    }
+
+    #[test]
+    fn similar_names_are_ordered_by_distance() {
+        let names = names(&[
+            (1, &["notch"]),
+            (2, &["dinnerbone"]),
+            (3, &["nitch"]),
+            (4, &["jeb_"]),
+        ]);
+        assert_eq!(ids(find_matches("notcch", &names)), ("similar", vec![1, 3]));
+        assert_eq!(ids(find_matches("dinerbone", &names)), ("similar", vec![2]));
+        assert_eq!(ids(find_matches("herobrine", &names)), ("similar", vec![]));
+    }
+}
@@ -0,0 +1,114 @@
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+
+use crate::commands::command::Command;
+use crate::commands::members::{current_members, display_label};
+use crate::commands::permissions::check_manage_guild;
+use crate::commands::resolve::resolve_member_arg;
+use crate::features::links;
+use crate::features::sync::{self, SyncError};
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        if !check_manage_guild(ctx, msg).await {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        let guild_id = msg.guild_id.unwrap();
+
+        let split: Vec<&str> = msg.content.split_whitespace().collect();
+        //a single member is synced right away
+        if split.len() > 1 {
+            let member_id = match resolve_member_arg(&ctx, &msg, &split[1..].join(" ")).await {
+                Some(member_id) => member_id,
+                None => return,
+            };
+            let guild = guild_id.to_guild_cached(&ctx).await;
+            if guild.is_none() {
+                send_err(ctx, msg).await;
+                return;
+            }
+            let guild = guild.unwrap();
+            let result = sync::sync_member(&ctx, &guild, member_id).await;
+            //looked up after the sync so a changed nickname already shows
+            let link = REDIS_CLIENT
+                .get_connection()
+                .and_then(|mut con| links::get_link(&mut con, guild_id.0, member_id))
+                .unwrap_or(None);
+            let label = match guild_id.member(&ctx, member_id).await {
+                Ok(member) => display_label(&guild, &member, link.as_ref()),
+                Err(_) => "Unknown user".to_string(),
+            };
+            let message = match result {
+                Ok(changes) if changes.is_empty() => format!("{} is already up to date.", label),
+                Ok(changes) => format!("Updated {}: {}.", label, changes.join(", ")),
+                Err(SyncError::NotLinked) => format!(
+                    "{} has no stored Minecraft account, they have to verify again.",
+                    label
+                ),
+                Err(err) => format!("Resyncing {} failed: {}", label, err),
+            };
+            say_something(message, ctx, msg).await;
+            return;
+        }
+
+        let members = REDIS_CLIENT
+            .get_connection()
+            .and_then(|mut con| links::linked_members(&mut con, guild_id.0));
+        let current = current_members(&ctx, guild_id).await;
+        if members.is_err() || current.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        //members that left are skipped by the sync
+        let current = current.unwrap();
+        let linked = members
+            .unwrap()
+            .into_iter()
+            .filter(|member_id| current.iter().any(|member| member.user.id.0 == *member_id))
+            .count();
+        say_something(
+            format!(
+                "Resyncing {} linked members, this can take a while.",
+                linked
+            ),
+            ctx.clone(),
+            msg.clone(),
+        )
+        .await;
+        //the whole guild takes longer than a command should block
+        tokio::spawn(async move {
+            let message = match sync::sync_guild(&ctx, guild_id).await {
+                Some(summary) => format!(
+                    "Resynced {} members: {} changed, {} failed.",
+                    summary.synced, summary.changed, summary.failed
+                ),
+                None => "Another resync is already running, try again once it is done.".to_string(),
+            };
+            say_something(message, ctx, msg).await;
+        });
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
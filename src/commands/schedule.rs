@@ -0,0 +1,161 @@
+use chrono_tz::Tz;
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+
+use crate::commands::command::Command;
+use crate::commands::permissions::check_manage_guild;
+use crate::features::periods::Period;
+use crate::features::schedule::{self, Schedule, LAST_MONTHLY_DAY};
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        if !check_manage_guild(ctx, msg).await {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        let guild_id = msg.guild_id.unwrap().0;
+
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let mut con = con.unwrap();
+
+        let usage = format!(
+            "`{} <#channel> week <weekday> <HH:MM>`, `{} <#channel> month <1-{}> <HH:MM>` or `{} off`",
+            command, command, LAST_MONTHLY_DAY, command
+        );
+        let args: Vec<&str> = msg.content.split_whitespace().skip(1).collect();
+        //no arguments shows the current schedule
+        if args.is_empty() {
+            let current = schedule::get_schedule(&mut con, guild_id);
+            let timezone = schedule::guild_timezone(&mut con, guild_id);
+            if current.is_err() || timezone.is_err() {
+                send_err(ctx, msg).await;
+                return;
+            }
+            let message = match current.unwrap() {
+                Some(current) => format!(
+                    "The {} leaderboard is posted in <#{}> {} ({}).",
+                    period_arg(current.period),
+                    current.channel_id,
+                    current.describe(),
+                    timezone.unwrap().name()
+                ),
+                None => format!(
+                    "No leaderboard posts are scheduled, set them up with {}.",
+                    usage
+                ),
+            };
+            say_something(message, ctx, msg).await;
+            return;
+        }
+        if args == ["off"] {
+            if schedule::remove_schedule(&mut con, guild_id).is_err() {
+                send_err(ctx, msg).await;
+                return;
+            }
+            say_something(
+                "Scheduled leaderboard posts are turned off.".to_string(),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+
+        let new = parse_schedule(&args);
+        if new.is_none() {
+            say_something(format!("Invalid usage: {}", usage), ctx, msg).await;
+            return;
+        }
+        let new = new.unwrap();
+        let next = schedule::set_schedule(&mut con, guild_id, &new);
+        if next.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let next = match next.unwrap() {
+            Some(next) => format!(
+                ", the next one is on {}",
+                next.format("%A, %Y-%m-%d %H:%M %Z")
+            ),
+            None => String::new(),
+        };
+        let timezone = schedule::guild_timezone(&mut con, guild_id).unwrap_or(Tz::UTC);
+        say_something(
+            format!(
+                "The {} leaderboard will be posted in <#{}> {} ({}){}.",
+                period_arg(new.period),
+                new.channel_id,
+                new.describe(),
+                timezone.name(),
+                next
+            ),
+            ctx,
+            msg,
+        )
+        .await;
+    }
+}
+
+///Parses `<#channel> <week|month> <day> <HH:MM>`.
+fn parse_schedule(args: &[&str]) -> Option<Schedule> {
+    if args.len() != 4 {
+        return None;
+    }
+    let channel_id = args[0]
+        .strip_prefix("<#")
+        .and_then(|arg| arg.strip_suffix('>'))
+        .and_then(|id| id.parse::<u64>().ok())?;
+    let period = Period::from_arg(args[1])?;
+    let day = match period {
+        Period::Week => schedule::parse_weekday(args[2])?,
+        Period::Month => match args[2].parse::<u32>() {
+            Ok(day) if (1..=LAST_MONTHLY_DAY).contains(&day) => day,
+            _ => return None,
+        },
+        Period::AllTime | Period::Season => return None,
+    };
+    let (hour, minute) = args[3].split_once(':')?;
+    let hour = hour.parse::<u32>().ok().filter(|hour| *hour < 24)?;
+    let minute = minute.parse::<u32>().ok().filter(|minute| *minute < 60)?;
+    Some(Schedule {
+        channel_id,
+        period,
+        day,
+        hour,
+        minute,
+    })
+}
+
+fn period_arg(period: Period) -> &'static str {
+    match period {
+        Period::Month => "monthly",
+        _ => "weekly",
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
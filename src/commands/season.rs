@@ -0,0 +1,150 @@
+use redis::{Connection, RedisResult};
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+
+use crate::commands::command::Command;
+use crate::commands::members::find_role;
+use crate::commands::permissions::check_manage_guild;
+use crate::features::{leaderboard, seasons};
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+    pub leaderboard_command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        if !check_manage_guild(ctx, msg).await {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        let guild_id = msg.guild_id.unwrap();
+
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let mut con = con.unwrap();
+
+        let args: Vec<&str> = msg.content.split_whitespace().skip(1).collect();
+        //no arguments shows the current season
+        if args.is_empty() {
+            let current = seasons::current_season(&mut con, guild_id.0);
+            let members = leaderboard::member_count(&mut con, &seasons::season_key(guild_id.0));
+            if current.is_err() || members.is_err() {
+                send_err(ctx, msg).await;
+                return;
+            }
+            let current = current.unwrap();
+            say_something(
+                format!(
+                    "Season {} ({}), {} members sent messages so far.",
+                    current.number,
+                    current.describe(),
+                    members.unwrap()
+                ),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+
+        //`close [top] [role]`, the role is everything after the number so names can contain spaces
+        let top = args.get(1).map(|top| top.parse::<u64>());
+        let award = match top {
+            _ if args[0] != "close" => Err(()),
+            None => Ok(None),
+            Some(Ok(top)) if top > 0 && args.len() > 2 => {
+                let guild = guild_id.to_guild_cached(&ctx).await;
+                match guild.and_then(|guild| find_role(&guild, &args[2..].join(" "))) {
+                    Some(role_id) => Ok(Some((top, role_id))),
+                    None => {
+                        say_something("Invalid Role".to_string(), ctx, msg).await;
+                        return;
+                    }
+                }
+            }
+            _ => Err(()),
+        };
+        if award.is_err() {
+            say_something(
+                format!(
+                    "Invalid usage: `{} close [top] [role]`, e.g. `{} close 3 @Champion`",
+                    command, command
+                ),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+
+        let number = seasons::close_season(&mut con, guild_id.0);
+        if number.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let number = number.unwrap();
+        let mut message = format!(
+            "Closed season {}, see `{}{} season {}` for the final standings.",
+            number, self.prefix, self.leaderboard_command, number
+        );
+        if let Some((top, role_id)) = award.unwrap() {
+            let winners = winners(&mut con, guild_id.0, number, top);
+            if winners.is_err() {
+                send_err(ctx, msg).await;
+                return;
+            }
+            let winners = winners.unwrap();
+            let mut awarded = 0;
+            for member_id in &winners {
+                //members that left in the meantime can't get the role
+                let added = ctx
+                    .http
+                    .add_member_role(guild_id.0, *member_id, role_id.0)
+                    .await;
+                if added.is_ok() {
+                    awarded += 1;
+                }
+            }
+            message.push_str(&format!(
+                "\nAwarded <@&{}> to {} of the top {} members.",
+                role_id, awarded, top
+            ));
+        }
+        say_something(message, ctx, msg).await;
+    }
+}
+
+///Members in the top `top` places of the archived season, ties on the last place are all included.
+fn winners(con: &mut Connection, guild_id: u64, number: u64, top: u64) -> RedisResult<Vec<u64>> {
+    let key = seasons::archive_key(guild_id, number);
+    let members = leaderboard::member_count(con, &key)?;
+    let entries = leaderboard::range(con, &key, 0, members)?;
+    Ok(entries
+        .iter()
+        .take_while(|entry| entry.rank <= top)
+        .map(|entry| entry.member_id)
+        .collect())
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
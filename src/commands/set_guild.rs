@@ -1,4 +1,5 @@
 use crate::commands::command::Command;
+use crate::commands::permissions::check_manage_guild;
 use crate::say_something;
 use crate::REDIS_CLIENT;
 use serenity::async_trait;
@@ -16,42 +17,11 @@ impl Command for CommandArgs {
         if !msg.content.starts_with(&command) {
             return;
         }
-        let ctx = ctx.clone();
-        let msg = msg.clone();
-
-        //get member object to check for perms
-        let guild = msg.guild(&ctx).await;
-        if guild.is_none() {
-            send_err(ctx, msg).await;
-            return;
-        }
-
-        let member = guild
-            .clone()
-            .unwrap()
-            .member(&ctx.http, msg.author.id)
-            .await;
-        if member.is_err() {
-            send_err(ctx, msg).await;
-            return;
-        }
-        let member = member.unwrap();
-        let perms = member.permissions(&ctx).await;
-        if perms.is_err() {
-            send_err(ctx, msg).await;
-            return;
-        }
-        //check for correct perms
-        let perms = perms.unwrap();
-        if !perms.manage_guild() {
-            say_something(
-                "You need the \"Manage Guild\" Permission to use this command".to_string(),
-                ctx,
-                msg,
-            )
-            .await;
+        if !check_manage_guild(ctx, msg).await {
             return;
         }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
 
         let split: Vec<&str> = msg.content.split(" ").collect();
         let size = split.clone().len();
@@ -61,7 +31,7 @@ impl Command for CommandArgs {
         }
 
         let guild_name = split[2];
-        let res = save_to_db(guild.unwrap().id.to_string(), guild_name.to_string()).await;
+        let res = save_to_db(msg.guild_id.unwrap().to_string(), guild_name.to_string()).await;
         if !res {
             send_err(ctx, msg).await;
             return;
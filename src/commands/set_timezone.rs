@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+use chrono_tz::Tz;
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+
+use crate::commands::command::Command;
+use crate::commands::permissions::check_manage_guild;
+use crate::features::schedule;
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        if !check_manage_guild(ctx, msg).await {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+
+        let split: Vec<&str> = msg.content.split_whitespace().collect();
+        let timezone = match split.get(1).map(|name| Tz::from_str(name)) {
+            Some(Ok(timezone)) if split.len() == 2 => timezone,
+            _ => {
+                say_something(
+                    format!(
+                        "Invalid usage: `{} [timezone]`, e.g. `{} Europe/Berlin`",
+                        command, command
+                    ),
+                    ctx,
+                    msg,
+                )
+                .await;
+                return;
+            }
+        };
+
+        let guild_id = msg.guild_id.unwrap().0;
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let mut con = con.unwrap();
+        //scheduled posts move to the same local time in the new timezone
+        let res = schedule::set_guild_timezone(&mut con, guild_id, timezone)
+            .and_then(|_| schedule::reschedule(&mut con, guild_id));
+        if res.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        say_something(
+            format!("Successfully set the timezone to {}.", timezone.name()),
+            ctx,
+            msg,
+        )
+        .await;
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
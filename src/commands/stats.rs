@@ -0,0 +1,168 @@
+use std::cmp::Reverse;
+
+use chrono::Weekday;
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use string_builder::Builder;
+
+use crate::commands::command::Command;
+use crate::features::activity::{self, ActivitySummary};
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+//days shown in the chart
+const DAYS: i64 = 30;
+//width of the longest bar in the chart
+const BAR_WIDTH: i64 = 20;
+//entries shown for busiest hours and channels
+const TOP_ENTRIES: usize = 5;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        if msg.guild_id.is_none() {
+            return;
+        }
+
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let summary = activity::summarize(&mut con.unwrap(), msg.guild_id.unwrap().0, DAYS);
+        if summary.is_err() {
+            println!("Error while reading activity: {:?}", summary.err());
+            send_err(ctx, msg).await;
+            return;
+        }
+        let summary = summary.unwrap();
+        if summary.days.iter().all(|(_, messages)| *messages == 0) {
+            say_something(
+                "There is no activity stored for this Server yet.".to_string(),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+
+        let chart = render_chart(&summary);
+        let hours = render_hours(&summary);
+        let channels = render_channels(&summary);
+        let chatters = format!(
+            "{} new, {} returning",
+            summary.new_chatters, summary.returning_chatters
+        );
+        let _ = msg
+            .channel_id
+            .send_message(&ctx.http, |m| {
+                m.embed(|e| {
+                    e.title(format!("Server activity of the last {} days", DAYS))
+                        .description(chart)
+                        .field("Busiest hours (UTC)", hours, true)
+                        .field("Most active channels", channels, true)
+                        .field("Chatters", chatters, false)
+                });
+                m
+            })
+            .await;
+    }
+}
+
+fn render_chart(summary: &ActivitySummary) -> String {
+    let max = summary
+        .days
+        .iter()
+        .map(|(_, messages)| *messages)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let mut builder = Builder::default();
+    builder.append("```\n");
+    for (day, messages) in &summary.days {
+        let bar = "█".repeat((messages * BAR_WIDTH / max) as usize);
+        builder.append(format!(
+            "{} {:<width$} {}\n",
+            day.format("%m-%d"),
+            bar,
+            messages,
+            width = BAR_WIDTH as usize
+        ));
+    }
+    builder.append("```");
+    builder.string().unwrap()
+}
+
+fn render_hours(summary: &ActivitySummary) -> String {
+    let mut slots = Vec::new();
+    for (weekday, hours) in summary.hours.iter().enumerate() {
+        for (hour, messages) in hours.iter().enumerate() {
+            if *messages > 0 {
+                slots.push((weekday, hour, *messages));
+            }
+        }
+    }
+    slots.sort_by_key(|slot| Reverse(slot.2));
+    let lines: Vec<String> = slots
+        .iter()
+        .take(TOP_ENTRIES)
+        .map(|(weekday, hour, messages)| {
+            format!(
+                "{:?} {:02}:00: {}",
+                weekday_from_index(*weekday),
+                hour,
+                messages
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        return "-".to_string();
+    }
+    lines.join("\n")
+}
+
+fn render_channels(summary: &ActivitySummary) -> String {
+    let lines: Vec<String> = summary
+        .channels
+        .iter()
+        .take(TOP_ENTRIES)
+        .map(|(channel_id, messages)| format!("<#{}>: {}", channel_id, messages))
+        .collect();
+    if lines.is_empty() {
+        return "-".to_string();
+    }
+    lines.join("\n")
+}
+
+fn weekday_from_index(index: usize) -> Weekday {
+    match index {
+        0 => Weekday::Mon,
+        1 => Weekday::Tue,
+        2 => Weekday::Wed,
+        3 => Weekday::Thu,
+        4 => Weekday::Fri,
+        5 => Weekday::Sat,
+        _ => Weekday::Sun,
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
@@ -0,0 +1,92 @@
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use string_builder::Builder;
+
+use crate::commands::command::Command;
+use crate::commands::members::member_labels;
+use crate::features::streaks;
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        if msg.guild_id.is_none() {
+            return;
+        }
+
+        let longest = msg.content.split_whitespace().nth(1) == Some("longest");
+        let labels = member_labels(&ctx, msg.guild_id.unwrap()).await;
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() || labels.is_none() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let labels = labels.unwrap();
+        let members: Vec<u64> = labels.keys().copied().collect();
+        let top = streaks::top_streaks(
+            &mut con.unwrap(),
+            msg.guild_id.unwrap().0,
+            longest,
+            &members,
+            10,
+        );
+        if top.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+
+        let mut builder = Builder::default();
+        for (position, (member_id, streak)) in top.unwrap().iter().enumerate() {
+            builder.append(format!(
+                "{}. {} with {} days\n",
+                position + 1,
+                labels[member_id],
+                streak
+            ));
+        }
+        if builder.len() == 0 {
+            say_something(
+                "There are currently no streaks for this Server.".to_string(),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+        let title = if longest {
+            "Longest streaks"
+        } else {
+            "Current streaks"
+        };
+        let message = builder.string().unwrap();
+        let _ = msg
+            .channel_id
+            .send_message(&ctx.http, |m| {
+                m.embed(|e| e.title(title).description(message));
+                m
+            })
+            .await;
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
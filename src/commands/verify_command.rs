@@ -3,23 +3,34 @@ use std::time::Duration;
 use redis::Commands;
 use redis::ErrorKind;
 use redis::RedisResult;
-use serde_json::Value;
 use serenity::async_trait;
 use serenity::model::channel::Message;
 use serenity::model::guild::Role;
 use serenity::prelude::*;
+use tokio::join;
+use tokio::time::timeout;
 
 use crate::commands::command::Command;
+use crate::commands::hypixel_queue::announce_position;
 use crate::commands::verify_command::PossibleErrors::{
-    DiscordNotLinked, HypixelAPIError, InvalidUsername, MojangAPIError,
+    DiscordNotLinked, HypixelAPIError, HypixelRateLimited, InvalidUsername, MojangAPIError,
+    TimedOut,
 };
+use crate::features::links::{self, Link};
+use crate::features::message_counting::unix_now;
+use crate::hypixel::cache::Endpoint;
+use crate::hypixel::error::ApiError;
+use crate::hypixel::models::HypixelRank;
 use crate::say_something;
-use crate::REDIS_CLIENT;
+use crate::{HYPIXEL_CLIENT, REDIS_CLIENT};
+
+//the whole lookup is given up after this, the requests themselves time out after 20 seconds
+const DEADLINE: Duration = Duration::from_secs(30);
+const QUEUED_DEADLINE: Duration = Duration::from_secs(90);
 
 pub struct VerifyCommandArgs {
     pub prefix: String,
     pub command: String,
-    pub api_key: String,
     pub role_name: String,
 }
 
@@ -59,9 +70,13 @@ impl Command for VerifyCommandArgs {
             return;
         }
         //get discord linked to username
-        let api_key = &self.api_key;
-        let api_key = api_key.clone();
-        let info = get_info(String::from(username), api_key).await;
+        let guild_id = msg.guild_id.map_or(0, |guild_id| guild_id.0);
+        //typing until the reply is sent, a failed indicator doesn't matter
+        let typing = msg.channel_id.start_typing(&ctx.http).ok();
+        let info = get_info(String::from(username), guild_id, &ctx, &msg).await;
+        if let Some(typing) = typing {
+            typing.stop();
+        }
         if info.is_err() {
             let error = info.err().unwrap();
             if error == PossibleErrors::DiscordNotLinked {
@@ -73,6 +88,14 @@ impl Command for VerifyCommandArgs {
                 say_something("There was an Error while contacting the Mojang API or it returned bad data (maybe an invalid Username). Please try again later.".to_string(), ctx, msg).await;
                 return;
             }
+            if error == PossibleErrors::TimedOut {
+                say_something("The Mojang or Hypixel API took too long to respond. Please try again later.".to_string(), ctx, msg).await;
+                return;
+            }
+            if error == PossibleErrors::HypixelRateLimited {
+                say_something("The Hypixel API limit is still reached, please try again in a minute.".to_string(), ctx, msg).await;
+                return;
+            }
             if error == PossibleErrors::HypixelAPIError {
                 say_something("There was an Error while contacting the Hypixel API or it returned bad data. Please try again later.".to_string(), ctx, msg).await;
                 return;
@@ -96,6 +119,7 @@ impl Command for VerifyCommandArgs {
         let rank = discord.rank;
         let username = discord.username;
         let user_guild = discord.guild;
+        let uuid = discord.uuid;
 
         let mut discriminator: String = msg.author.discriminator.to_string();
         if discriminator.len() == 1 {
@@ -116,6 +140,21 @@ impl Command for VerifyCommandArgs {
             say_something(format!("The linked Username `{}` doesn't match your Discord Username: `{}`. If you just changed this wait a bit and try again.", linked_discord, user_discord), ctx, msg).await;
             return;
         }
+        //remember the account before touching roles and the nickname, which can fail for members above the bot
+        let link = Link {
+            uuid,
+            username: username.clone(),
+            verified_at: unix_now(),
+            rank: rank.role_name().map(|rank| rank.to_string()),
+        };
+        if let Some(guild_id) = msg.guild_id {
+            let stored = REDIS_CLIENT.get_connection().and_then(|mut con| {
+                links::set_link(&mut con, guild_id.0, msg.author.id.0, &link)
+            });
+            if let Err(err) = stored {
+                println!("Error while storing the link of {}: {}", msg.author.id, err);
+            }
+        }
         //assign Verified role
         let member = msg.member(&ctx).await;
         if member.is_err() {
@@ -175,7 +214,7 @@ impl Command for VerifyCommandArgs {
                         }
 
                         //change username
-                        if let Err(_) = member.edit(&ctx, |m| m.nickname(username)).await {
+                        if let Err(_) = member.edit(&ctx, |m| m.nickname(&username)).await {
                             say_something("The bot was unable to change your nickname. This probably has to do something with permissions: Make sure the bot is over you in the Role hierarchy otherwise it can't change your nickname.".to_string(), ctx, msg).await;
                             return;
                         }
@@ -228,38 +267,23 @@ impl Command for VerifyCommandArgs {
 #[derive(PartialEq)]
 enum PossibleErrors {
     HypixelAPIError,
+    HypixelRateLimited,
     MojangAPIError,
+    TimedOut,
     DiscordNotLinked,
     InvalidUsername,
 }
 
-#[derive(PartialEq)]
-enum HypixelRanks {
-    Default,
-    Vip,
-    Vipplus,
-    Mvp,
-    Mvpplus,
-    Mvpplusplus,
-}
-
 struct ApiInfo {
+    uuid: String,
     discord: String,
-    rank: HypixelRanks,
+    rank: HypixelRank,
     username: String,
     guild: String,
 }
 
-async fn get_rank_role(rank: HypixelRanks, ctx: &Context, msg: &Message) -> Option<Role> {
-    let rank_string;
-    match rank {
-        HypixelRanks::Mvpplusplus => rank_string = "MVP++",
-        HypixelRanks::Mvpplus => rank_string = "MVP+",
-        HypixelRanks::Mvp => rank_string = "MVP",
-        HypixelRanks::Vipplus => rank_string = "VIP+",
-        HypixelRanks::Vip => rank_string = "VIP",
-        HypixelRanks::Default => return None,
-    }
+async fn get_rank_role(rank: HypixelRank, ctx: &Context, msg: &Message) -> Option<Role> {
+    let rank_string = rank.role_name()?;
     if let Some(guild_id) = msg.guild_id {
         if let Some(guild) = guild_id.to_guild_cached(&ctx).await {
             if let Some(role_id) = guild.role_by_name(rank_string) {
@@ -270,160 +294,62 @@ async fn get_rank_role(rank: HypixelRanks, ctx: &Context, msg: &Message) -> Opti
     None
 }
 
-async fn get_guild(player_uuid: String, api_key: String) -> String {
-    let url = format!(
-        "https://api.hypixel.net/guild?key={}&player={}",
-        api_key, player_uuid
-    );
-
-    let client = reqwest::ClientBuilder::new()
-        .timeout(Duration::from_secs(20))
-        .build()
-        .unwrap();
-
-    let response = client.get(url).send().await;
-
-    if response.is_err() {
-        return "".to_string();
-    }
-
-    let json = serde_json::from_str(response.unwrap().text().await.unwrap().as_str());
-    if json.is_err() {
-        return "".to_string();
-    }
-    let json: Value = json.expect("");
-    if let Some(guild) = json.get("guild") {
-        if let Some(name) = guild.get("name") {
-            return name.as_str().unwrap().to_string();
-        }
-    }
-
-    "".to_string()
-}
-
-fn get_rank(api_response: &Value) -> HypixelRanks {
-    //based on my API Wrapper in Java: https://github.com/Lulonaut/HypixelAPIWrapper/blob/d43c73c00f2bc111cf407c6a325cb686a3ec899a/src/main/java/de/lulonaut/wrapper/utils/getStuff.java#L19
-    let player = api_response.get("player").unwrap();
-
-    //check for owner and other weird ranks (eg: Technoblade Pig rank)
-    if player.get("prefix").is_some() {
-        return HypixelRanks::Default;
-    }
-    //check for staff
-    if let Some(rank) = player.get("rank") {
-        if rank == "HELPER" || rank == "MODERATOR" || rank == "ADMIN" || rank == "YOUTUBER" {
-            return HypixelRanks::Default;
-        }
-    }
-    //check for MVP++
-    if let Some(mpr) = player.get("monthlyPackageRank") {
-        if mpr.as_str().unwrap() == "SUPERSTAR" {
-            return HypixelRanks::Mvpplusplus;
-        }
-    }
-    if let Some(rank) = player.get("newPackageRank") {
-        let rank = rank.as_str().unwrap();
-        match rank {
-            "MVP_PLUS" => return HypixelRanks::Mvpplus,
-            "MVP" => return HypixelRanks::Mvp,
-            "VIP_PLUS" => return HypixelRanks::Vipplus,
-            "VIP" => return HypixelRanks::Vip,
-            _ => {}
-        }
-    }
-
-    HypixelRanks::Default
-}
-
-fn get_username(api_response: &Value) -> String {
-    return api_response
-        .get("player")
-        .unwrap()
-        .get("displayname")
-        .unwrap()
-        .as_str()
-        .unwrap()
-        .to_string();
-}
-
-async fn get_info(username: String, api_key: String) -> Result<ApiInfo, PossibleErrors> {
-    let client = reqwest::ClientBuilder::new()
-        .timeout(Duration::from_secs(20))
-        .build()
-        .unwrap();
-
-    let uuid_response = client
-        .get(format!(
-            "https://api.mojang.com/users/profiles/minecraft/{}",
-            username
-        ))
-        .send()
-        .await;
-
-    if uuid_response.is_err() {
-        return Err(MojangAPIError);
-    }
-
-    let json = serde_json::from_str(uuid_response.unwrap().text().await.unwrap().as_str());
-    if json.is_err() {
-        return Err(MojangAPIError);
-    }
-    let json: Value = json.expect("");
-
-    //check for invalid username
-    if json.get("error").is_some() {
-        return Err(InvalidUsername);
-    }
-
-    let mut uuid;
-    match json.get("id") {
-        Some(v) => uuid = v.to_string(),
-        None => return Err(MojangAPIError),
-    }
-    uuid = uuid.replace("\"", "");
-
-    let response = client
-        .get(format!(
-            "https://api.hypixel.net/player?key={}&uuid={}",
-            api_key, uuid
-        ))
-        .send()
-        .await;
-
-    if response.is_err() {
-        return Err(HypixelAPIError);
-    }
-
-    let json = serde_json::from_str(response.unwrap().text().await.unwrap().as_str());
-    if json.is_err() {
-        return Err(HypixelAPIError);
-    }
-    let json: Value = json.expect("");
-    if json.get("player").is_some() {
-    } else {
-        return Err(HypixelAPIError);
-    }
-    let rank = get_rank(&json);
-    let username = get_username(&json);
-    let guild = get_guild(uuid, api_key).await;
-
-    if let Some(player) = json.get("player") {
-        if let Some(social_media) = player.get("socialMedia") {
-            if let Some(links) = social_media.get("links") {
-                if let Some(discord) = links.get("DISCORD") {
-                    //slice quotation marks off string
-                    let sliced = &discord.to_string()[1..discord.to_string().len() - 1];
-                    return Ok(ApiInfo {
-                        discord: sliced.to_string(),
-                        rank,
-                        username,
-                        guild,
-                    });
-                }
+async fn get_info(
+    username: String,
+    guild_id: u64,
+    ctx: &Context,
+    msg: &Message,
+) -> Result<ApiInfo, PossibleErrors> {
+    let profile = timeout(DEADLINE, HYPIXEL_CLIENT.profile(&username))
+        .await
+        .map_err(|_| TimedOut)?
+        .map_err(|err| match err {
+            ApiError::UnknownUsername => InvalidUsername,
+            err => {
+                println!("Error while looking up {}: {}", username, err);
+                MojangAPIError
             }
+        })?;
+    let queued = announce_position(
+        ctx,
+        msg,
+        &profile.id,
+        &[Endpoint::Player, Endpoint::Guild],
+        "verification",
+    )
+    .await;
+    //a queued lookup has to wait for the next quota window
+    let deadline = if queued { QUEUED_DEADLINE } else { DEADLINE };
+    //both only need the UUID, so they are requested at the same time
+    let (player, guild) = timeout(deadline, async {
+        join!(
+            HYPIXEL_CLIENT.player(&profile.id, guild_id),
+            HYPIXEL_CLIENT.guild_of(&profile.id, guild_id)
+        )
+    })
+    .await
+    .map_err(|_| TimedOut)?;
+    let player = player.map_err(|err| match err {
+        ApiError::RateLimited => HypixelRateLimited,
+        err => {
+            println!("Error while fetching the Hypixel player {}: {}", profile.id, err);
+            HypixelAPIError
         }
-    } else {
-        return Err(HypixelAPIError);
+    })?;
+    //a missing guild only means the Guild Member role isn't given
+    let guild = match guild {
+        Ok(Some(guild)) => guild.name,
+        _ => "".to_string(),
+    };
+
+    match player.discord() {
+        Some(discord) => Ok(ApiInfo {
+            uuid: profile.id.clone(),
+            discord: discord.to_string(),
+            rank: player.rank(),
+            username: player.displayname.clone().unwrap_or(profile.name),
+            guild,
+        }),
+        None => Err(DiscordNotLinked),
     }
-    Err(DiscordNotLinked)
 }
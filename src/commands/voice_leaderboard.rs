@@ -0,0 +1,85 @@
+use serenity::async_trait;
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use string_builder::Builder;
+
+use crate::commands::command::Command;
+use crate::commands::members::member_labels;
+use crate::features::voice_tracking;
+use crate::say_something;
+use crate::REDIS_CLIENT;
+
+pub struct CommandArgs {
+    pub prefix: String,
+    pub command: String,
+}
+
+#[async_trait]
+impl Command for CommandArgs {
+    async fn execute(&self, ctx: &Context, msg: &Message) {
+        let command = format!("{}{}", self.prefix, self.command);
+        if !msg.content.starts_with(&command) {
+            return;
+        }
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        if msg.guild_id.is_none() {
+            return;
+        }
+
+        let labels = member_labels(&ctx, msg.guild_id.unwrap()).await;
+        let con = REDIS_CLIENT.get_connection();
+        if con.is_err() || labels.is_none() {
+            send_err(ctx, msg).await;
+            return;
+        }
+        let labels = labels.unwrap();
+        let members: Vec<u64> = labels.keys().copied().collect();
+        let top = voice_tracking::top_voice_times(
+            &mut con.unwrap(),
+            msg.guild_id.unwrap().0,
+            &members,
+            10,
+        );
+        if top.is_err() {
+            send_err(ctx, msg).await;
+            return;
+        }
+
+        let mut builder = Builder::default();
+        for (position, (member_id, seconds)) in top.unwrap().iter().enumerate() {
+            builder.append(format!(
+                "{}. {} with {}\n",
+                position + 1,
+                labels[member_id],
+                voice_tracking::format_duration(*seconds)
+            ));
+        }
+        if builder.len() == 0 {
+            say_something(
+                "There is currently no voice activity stored for this Server.".to_string(),
+                ctx,
+                msg,
+            )
+            .await;
+            return;
+        }
+        let message = builder.string().unwrap();
+        let _ = msg
+            .channel_id
+            .send_message(&ctx.http, |m| {
+                m.embed(|e| e.title("Current voice leaderboard").description(message));
+                m
+            })
+            .await;
+    }
+}
+
+async fn send_err(ctx: Context, msg: Message) {
+    say_something(
+        "An internal Error occurred while processing this command.".to_string(),
+        ctx,
+        msg,
+    )
+    .await;
+}
@@ -0,0 +1,84 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use redis::{Commands, Connection, RedisResult};
+
+use crate::features::message_counting::{channels_key, daily_key, first_seen_key, hourly_key};
+
+//hours, channels and members of one day
+type DayBuckets = (HashMap<u32, i64>, Vec<(u64, i64)>, Vec<(u64, i64)>);
+
+///Activity of a guild over the last few days, read from the per day buckets.
+pub struct ActivitySummary {
+    ///Messages per day, oldest day first.
+    pub days: Vec<(NaiveDate, i64)>,
+    ///Messages per weekday (Monday first) and hour in UTC.
+    pub hours: [[i64; 24]; 7],
+    ///Messages per channel, most active first.
+    pub channels: Vec<(u64, i64)>,
+    ///Members whose first counted message is within the time span.
+    pub new_chatters: usize,
+    ///Members who were active within the time span but already chatted before.
+    pub returning_chatters: usize,
+}
+
+pub fn summarize(con: &mut Connection, guild_id: u64, days: i64) -> RedisResult<ActivitySummary> {
+    let today = Utc::today().naive_utc();
+    let first_day = today - Duration::days(days - 1);
+
+    let mut summary = ActivitySummary {
+        days: Vec::new(),
+        hours: [[0; 24]; 7],
+        channels: Vec::new(),
+        new_chatters: 0,
+        returning_chatters: 0,
+    };
+    let mut channels: HashMap<u64, i64> = HashMap::new();
+    let mut chatters: HashSet<u64> = HashSet::new();
+
+    let mut day = first_day;
+    while day <= today {
+        let (hourly, day_channels, members): DayBuckets = redis::pipe()
+            .hgetall(hourly_key(guild_id, day))
+            .zrange_withscores(channels_key(guild_id, day), 0, -1)
+            .zrange_withscores(daily_key(guild_id, day), 0, -1)
+            .query(con)?;
+
+        let weekday = day.weekday().num_days_from_monday() as usize;
+        let mut total = 0;
+        for (hour, messages) in hourly {
+            if let Some(slot) = summary.hours[weekday].get_mut(hour as usize) {
+                *slot += messages;
+            }
+            total += messages;
+        }
+        summary.days.push((day, total));
+        for (channel_id, messages) in day_channels {
+            *channels.entry(channel_id).or_insert(0) += messages;
+        }
+        chatters.extend(
+            members
+                .into_iter()
+                .filter(|(_, messages)| *messages > 0)
+                .map(|(member_id, _)| member_id),
+        );
+        day = day.succ();
+    }
+
+    summary.channels = channels
+        .into_iter()
+        .filter(|(_, messages)| *messages > 0)
+        .collect();
+    summary
+        .channels
+        .sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let first_timestamp = first_day.and_hms(0, 0, 0).timestamp();
+    let new: HashSet<u64> = con
+        .zrangebyscore::<_, _, _, Vec<u64>>(first_seen_key(guild_id), first_timestamp, "+inf")?
+        .into_iter()
+        .collect();
+    summary.new_chatters = chatters.intersection(&new).count();
+    summary.returning_chatters = chatters.len() - summary.new_chatters;
+    Ok(summary)
+}
@@ -0,0 +1,334 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use redis::{Commands, Connection, RedisResult};
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, MessageId};
+
+use crate::features::message_counting::{
+    count_message, messages_key, remember_counted, unix_now, CountedMessage,
+};
+use crate::{COUNTED_MESSAGE_WINDOW, REDIS_CLIENT};
+
+//discord returns at most 100 messages per history request
+const PAGE_SIZE: u64 = 100;
+//pause between history requests on top of serenity's own rate limit handling
+const PAGE_DELAY: Duration = Duration::from_millis(500);
+//edit the status message every few pages instead of on every request
+const PROGRESS_INTERVAL: u64 = 5;
+//milliseconds between the unix epoch and the first second of 2015 (the discord epoch)
+const DISCORD_EPOCH: u64 = 1_420_070_400_000;
+
+lazy_static! {
+    //guilds that currently have a backfill job running
+    static ref RUNNING_JOBS: Mutex<HashSet<u64>> = Mutex::new(HashSet::new());
+}
+
+///A range of a channel's history that was not counted live: every message with `after < id < before`.
+pub struct Gap {
+    pub channel_id: u64,
+    pub after: u64,
+    pub before: u64,
+}
+
+pub struct Progress {
+    pub state: String,
+    pub pages: u64,
+    pub counted: u64,
+    pub pending_gaps: usize,
+}
+
+///Remembers a range of history that has to be backfilled before the counts for this channel are complete.
+pub fn register_gap(con: &mut Connection, guild_id: u64, gap: &Gap) {
+    if gap.before <= gap.after + 1 {
+        return;
+    }
+    let _: RedisResult<()> = con.hset(
+        gaps_key(guild_id),
+        format!("{}:{}", gap.channel_id, gap.after),
+        gap.before,
+    );
+}
+
+pub fn pending_gaps(con: &mut Connection, guild_id: u64) -> RedisResult<Vec<Gap>> {
+    let stored: Vec<(String, u64)> = con.hgetall(gaps_key(guild_id))?;
+    let mut gaps: Vec<Gap> = stored
+        .into_iter()
+        .filter_map(|(field, before)| {
+            let mut split = field.split(':');
+            let channel_id = split.next()?.parse().ok()?;
+            let after = split.next()?.parse().ok()?;
+            Some(Gap {
+                channel_id,
+                after,
+                before,
+            })
+        })
+        .collect();
+    gaps.sort_by_key(|gap| (gap.channel_id, gap.after));
+    Ok(gaps)
+}
+
+///Where the history of a channel without a counted message starts, `first_live` is the first message that is counted live.
+///Guilds that were counted live before the last message per channel was tracked already have their history in the counts,
+///so only what comes after the first message seen since then is missing.
+pub fn history_start(con: &mut Connection, guild_id: u64, first_live: u64) -> RedisResult<u64> {
+    let floor: Option<u64> = con.get(history_floor_key(guild_id))?;
+    if let Some(floor) = floor {
+        return Ok(floor);
+    }
+    let tracked: bool = con.exists(channel_last_key(guild_id))?;
+    let counted: bool = con.exists(messages_key(guild_id))?;
+    if tracked || !counted {
+        return Ok(0);
+    }
+    //the first caller decides, concurrent ones read its floor
+    let _: () = con.set_nx(history_floor_key(guild_id), first_live)?;
+    con.get(history_floor_key(guild_id))
+}
+
+///Makes sure every given channel is covered, even if nothing was ever counted in it.
+pub fn prepare_channels(con: &mut Connection, guild_id: u64, channels: &[u64]) -> RedisResult<()> {
+    let gaps = pending_gaps(con, guild_id)?;
+    let now = snowflake_now();
+    for channel_id in channels {
+        if gaps.iter().any(|gap| gap.channel_id == *channel_id) {
+            continue;
+        }
+        let last: Option<u64> = con.hget(channel_last_key(guild_id), *channel_id)?;
+        if last.is_some() {
+            continue;
+        }
+        //everything up to now is history, everything after will be counted live
+        let after = history_start(con, guild_id, now)?;
+        register_gap(
+            con,
+            guild_id,
+            &Gap {
+                channel_id: *channel_id,
+                after,
+                before: now,
+            },
+        );
+        let _: () = con.hset(channel_last_key(guild_id), *channel_id, now)?;
+    }
+    Ok(())
+}
+
+pub fn get_progress(con: &mut Connection, guild_id: u64) -> RedisResult<Progress> {
+    let state: Option<String> = con.hget(progress_key(guild_id), "state")?;
+    let pages: Option<u64> = con.hget(progress_key(guild_id), "pages")?;
+    let counted: Option<u64> = con.hget(progress_key(guild_id), "counted")?;
+    Ok(Progress {
+        state: state.unwrap_or_else(|| "never run".to_string()),
+        pages: pages.unwrap_or(0),
+        counted: counted.unwrap_or(0),
+        pending_gaps: pending_gaps(con, guild_id)?.len(),
+    })
+}
+
+///Returns false if a job is already running for this guild.
+pub fn try_start(guild_id: u64) -> bool {
+    match RUNNING_JOBS.lock() {
+        Ok(mut running) => running.insert(guild_id),
+        Err(_) => false,
+    }
+}
+
+///Works through all pending gaps of the guild (or only the ones in `channels`) and reports progress by editing `status`.
+///The cursor of a gap is moved in the same transaction that adds the counts, so an interrupted job can be resumed without counting anything twice.
+pub async fn run(ctx: Context, guild_id: u64, channels: Option<Vec<u64>>, mut status: Message) {
+    let gaps = match REDIS_CLIENT.get_connection() {
+        Ok(mut con) => {
+            let _: RedisResult<()> = redis::pipe()
+                .hset(progress_key(guild_id), "state", "running")
+                .ignore()
+                .hset(progress_key(guild_id), "pages", 0)
+                .ignore()
+                .hset(progress_key(guild_id), "counted", 0)
+                .ignore()
+                .query(&mut con);
+            pending_gaps(&mut con, guild_id)
+        }
+        Err(err) => Err(err),
+    };
+    let gaps = match gaps {
+        Ok(gaps) => gaps
+            .into_iter()
+            .filter(|gap| match &channels {
+                Some(channels) => channels.contains(&gap.channel_id),
+                None => true,
+            })
+            .collect::<Vec<Gap>>(),
+        Err(err) => {
+            println!("Error while loading backfill gaps: {}", err);
+            finish(guild_id, "failed");
+            let _ = status
+                .edit(&ctx, |m| m.content("The backfill failed to start."))
+                .await;
+            return;
+        }
+    };
+
+    let mut pages = 0;
+    let mut counted = 0;
+    let mut failed_channels: Vec<u64> = Vec::new();
+    for gap in gaps {
+        let mut before = gap.before;
+        loop {
+            let page = ChannelId(gap.channel_id)
+                .messages(&ctx.http, |retriever| {
+                    retriever.before(MessageId(before)).limit(PAGE_SIZE)
+                })
+                .await;
+            let page = match page {
+                Ok(page) => page,
+                Err(err) => {
+                    println!(
+                        "Error while fetching history of channel {}: {}",
+                        gap.channel_id, err
+                    );
+                    failed_channels.push(gap.channel_id);
+                    break;
+                }
+            };
+            match save_page(guild_id, &gap, &page) {
+                Ok((page_counted, next_before)) => {
+                    pages += 1;
+                    counted += page_counted;
+                    match next_before {
+                        Some(next_before) => before = next_before,
+                        None => break,
+                    }
+                }
+                Err(err) => {
+                    println!("Error while saving backfilled counts: {}", err);
+                    failed_channels.push(gap.channel_id);
+                    break;
+                }
+            }
+            if pages % PROGRESS_INTERVAL == 0 {
+                let content = format!(
+                    "Backfilling <#{}>: {} pages read, {} messages counted so far.",
+                    gap.channel_id, pages, counted
+                );
+                let _ = status.edit(&ctx, |m| m.content(content)).await;
+            }
+            tokio::time::sleep(PAGE_DELAY).await;
+        }
+    }
+
+    let mut content = format!(
+        "Backfill finished: {} pages read, {} messages counted.",
+        pages, counted
+    );
+    if failed_channels.is_empty() {
+        finish(guild_id, "finished");
+    } else {
+        finish(guild_id, "interrupted");
+        let channels: Vec<String> = failed_channels
+            .iter()
+            .map(|channel| format!("<#{}>", channel))
+            .collect();
+        content.push_str(&format!(
+            " Could not read {}, run the command again to resume.",
+            channels.join(", ")
+        ));
+    }
+    let _ = status.edit(&ctx, |m| m.content(content)).await;
+}
+
+///Counts one page of history and moves the cursor of the gap. Returns the number of counted messages and the next cursor, or None if the gap is complete.
+fn save_page(guild_id: u64, gap: &Gap, page: &[Message]) -> RedisResult<(u64, Option<u64>)> {
+    let mut con = REDIS_CLIENT.get_connection()?;
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    let mut counted = 0;
+    let mut reached_end = (page.len() as u64) < PAGE_SIZE;
+    //deletions of messages sent within the window are reversed just like for live ones
+    let cutoff = unix_now().saturating_sub(*COUNTED_MESSAGE_WINDOW);
+    for message in page {
+        if message.id.0 <= gap.after {
+            reached_end = true;
+            continue;
+        }
+        if message.author.bot {
+            continue;
+        }
+        count_message(
+            &mut pipe,
+            &CountedMessage {
+                guild_id,
+                channel_id: gap.channel_id,
+                member_id: message.author.id.0,
+                sent_at: message.timestamp,
+            },
+        );
+        let sent_at = message.timestamp.timestamp().max(0) as u64;
+        if sent_at >= cutoff {
+            remember_counted(
+                &mut pipe,
+                guild_id,
+                message.id.0,
+                message.author.id.0,
+                sent_at,
+            );
+        }
+        counted += 1;
+    }
+    let field = format!("{}:{}", gap.channel_id, gap.after);
+    let next_before = match page.iter().map(|message| message.id.0).min() {
+        Some(oldest) if !reached_end => {
+            pipe.hset(gaps_key(guild_id), &field, oldest).ignore();
+            Some(oldest)
+        }
+        _ => {
+            pipe.hdel(gaps_key(guild_id), &field).ignore();
+            None
+        }
+    };
+    pipe.hincr(progress_key(guild_id), "pages", 1)
+        .ignore()
+        .hincr(progress_key(guild_id), "counted", counted)
+        .ignore();
+    pipe.query::<()>(&mut con)?;
+    Ok((counted, next_before))
+}
+
+fn finish(guild_id: u64, state: &str) {
+    if let Ok(mut con) = REDIS_CLIENT.get_connection() {
+        let _: RedisResult<()> = con.hset(progress_key(guild_id), "state", state);
+    }
+    release(guild_id);
+}
+
+pub fn release(guild_id: u64) {
+    if let Ok(mut running) = RUNNING_JOBS.lock() {
+        running.remove(&guild_id);
+    }
+}
+
+pub fn channel_last_key(guild_id: u64) -> String {
+    format!("verifybot:channel_last:{}", guild_id)
+}
+
+///Message id before which the guild's history is already counted.
+fn history_floor_key(guild_id: u64) -> String {
+    format!("verifybot:history_floor:{}", guild_id)
+}
+
+fn gaps_key(guild_id: u64) -> String {
+    format!("verifybot:backfill_gaps:{}", guild_id)
+}
+
+fn progress_key(guild_id: u64) -> String {
+    format!("verifybot:backfill_progress:{}", guild_id)
+}
+
+///A message id that is newer than every message sent before now.
+fn snowflake_now() -> u64 {
+    (unix_now() * 1000).saturating_sub(DISCORD_EPOCH) << 22
+}
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Mutex;
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+use lazy_static::lazy_static;
+use rusttype::{point, Font, Scale};
+use tokio::{join, task};
+
+use crate::features::levels::level_for;
+use crate::{http, HTTP_CLIENT};
+
+const BACKGROUND: Rgba<u8> = Rgba([35, 39, 42, 255]);
+const ROW_BACKGROUND: Rgba<u8> = Rgba([44, 47, 51, 255]);
+const TEXT: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const SUBTEXT: Rgba<u8> = Rgba([185, 187, 190, 255]);
+const BAR_BACKGROUND: Rgba<u8> = Rgba([72, 75, 81, 255]);
+const BAR_FILL: Rgba<u8> = Rgba([88, 101, 242, 255]);
+const RANK_COLOR: Rgba<u8> = Rgba([85, 255, 255, 255]);
+
+const PROFILE_WIDTH: u32 = 600;
+const PROFILE_HEIGHT: u32 = 180;
+const ROW_WIDTH: u32 = 600;
+const ROW_HEIGHT: u32 = 64;
+//cached images and cards are dropped all at once when there are more than this
+const MAX_CACHED: usize = 500;
+
+//guild and member id mapped to the fingerprint of the shown data and the rendered result
+type RenderCache<T> = Mutex<HashMap<(u64, u64), (String, T)>>;
+
+lazy_static! {
+    static ref FONT: Font<'static> = Font::try_from_bytes(include_bytes!("../../assets/DejaVuSans.ttf"))
+        .expect("The bundled font is invalid");
+    //downloaded avatars and heads per url
+    static ref IMAGE_CACHE: Mutex<HashMap<String, DynamicImage>> = Mutex::new(HashMap::new());
+    //rendered profile cards per guild and member together with the data they show
+    static ref PROFILE_CACHE: RenderCache<Vec<u8>> = Mutex::new(HashMap::new());
+    //rendered leaderboard rows per guild and member together with the data they show
+    static ref ROW_CACHE: RenderCache<RgbaImage> = Mutex::new(HashMap::new());
+}
+
+///Minecraft account of a verified member.
+#[derive(Clone)]
+pub struct MinecraftIdentity {
+    pub username: String,
+    pub rank: Option<String>,
+}
+
+///Everything a card shows about one member.
+#[derive(Clone)]
+pub struct CardData {
+    pub member_id: u64,
+    pub name: String,
+    pub avatar_url: String,
+    pub rank: Option<u64>,
+    pub messages: u64,
+    pub minecraft: Option<MinecraftIdentity>,
+}
+
+impl CardData {
+    ///Changes whenever anything on the card changes, a cached card is only used while this matches.
+    fn fingerprint(&self) -> String {
+        let (username, rank) = match &self.minecraft {
+            Some(minecraft) => (minecraft.username.as_str(), minecraft.rank.as_deref()),
+            None => ("", None),
+        };
+        format!(
+            "{}|{}|{:?}|{}|{}|{:?}",
+            self.name, self.avatar_url, self.rank, self.messages, username, rank
+        )
+    }
+}
+
+///PNG with avatar, name, rank, messages and level of a member, plus their Minecraft head and Hypixel rank if they are verified.
+pub async fn profile_card(guild_id: u64, data: &CardData) -> Option<Vec<u8>> {
+    let fingerprint = data.fingerprint();
+    if let Some(cached) = cached(&PROFILE_CACHE, (guild_id, data.member_id), &fingerprint) {
+        return Some(cached);
+    }
+
+    let head = data
+        .minecraft
+        .as_ref()
+        .map(|minecraft| head_url(&minecraft.username));
+    let (avatar, head) = join!(fetch_image(&data.avatar_url), async {
+        match &head {
+            Some(head) => fetch_image(head).await,
+            None => None,
+        }
+    });
+    //drawing and encoding take long enough to hold up other tasks
+    let member_id = data.member_id;
+    let data = data.clone();
+    let png =
+        task::spawn_blocking(move || encode(&draw_profile(&data, avatar.as_ref(), head.as_ref())))
+            .await
+            .ok()??;
+    store(
+        &PROFILE_CACHE,
+        (guild_id, member_id),
+        fingerprint,
+        png.clone(),
+    );
+    Some(png)
+}
+
+fn draw_profile(
+    data: &CardData,
+    avatar: Option<&DynamicImage>,
+    head: Option<&DynamicImage>,
+) -> RgbaImage {
+    let mut card = RgbaImage::from_pixel(PROFILE_WIDTH, PROFILE_HEIGHT, BACKGROUND);
+    if let Some(avatar) = avatar {
+        paste(&mut card, avatar, 26, 26, 128);
+    }
+    draw_text(&mut card, &data.name, 180, 22, 32.0, TEXT);
+    let rank = match data.rank {
+        Some(rank) => format!("Rank #{}", rank),
+        None => "Unranked".to_string(),
+    };
+    draw_text(
+        &mut card,
+        &format!("{}  -  {} messages", rank, data.messages),
+        180,
+        66,
+        20.0,
+        SUBTEXT,
+    );
+
+    if let Some(minecraft) = &data.minecraft {
+        if let Some(head) = head {
+            paste(&mut card, head, 528, 22, 48);
+        }
+        if let Some(rank) = &minecraft.rank {
+            let width = text_width(rank, 18.0);
+            draw_text(&mut card, rank, 552 - width / 2, 76, 18.0, RANK_COLOR);
+        }
+    }
+
+    //level bar
+    let level = level_for(data.messages);
+    draw_text(
+        &mut card,
+        &format!("Level {}", level.level),
+        180,
+        104,
+        18.0,
+        TEXT,
+    );
+    let progress = format!("{}/{}", level.progress, level.needed);
+    let width = text_width(&progress, 18.0);
+    draw_text(&mut card, &progress, 570 - width, 104, 18.0, SUBTEXT);
+    fill_rect(&mut card, 180, 132, 390, 20, BAR_BACKGROUND);
+    let filled = 390 * level.progress / level.needed.max(1);
+    fill_rect(&mut card, 180, 132, filled as u32, 20, BAR_FILL);
+    card
+}
+
+///PNG with one row per member in the order of `rows`.
+pub async fn leaderboard_card(guild_id: u64, rows: &[CardData]) -> Option<Vec<u8>> {
+    //avatars of the rows that aren't cached are downloaded at the same time
+    let downloads: Vec<_> = rows
+        .iter()
+        .map(|data| {
+            let fingerprint = data.fingerprint();
+            let row = cached(&ROW_CACHE, (guild_id, data.member_id), &fingerprint);
+            let avatar = match row {
+                Some(_) => None,
+                None => {
+                    let url = data.avatar_url.clone();
+                    Some(task::spawn(async move { fetch_image(&url).await }))
+                }
+            };
+            (data.clone(), fingerprint, row, avatar)
+        })
+        .collect();
+    let mut prepared = Vec::with_capacity(downloads.len());
+    for (data, fingerprint, row, avatar) in downloads {
+        let avatar = match avatar {
+            Some(avatar) => avatar.await.ok().flatten(),
+            None => None,
+        };
+        prepared.push((data, fingerprint, row, avatar));
+    }
+
+    task::spawn_blocking(move || {
+        let count = prepared.len() as u32;
+        let height = ROW_HEIGHT * count + 8 * (count + 1);
+        let mut card = RgbaImage::from_pixel(ROW_WIDTH + 16, height, BACKGROUND);
+        for (index, (data, fingerprint, row, avatar)) in prepared.into_iter().enumerate() {
+            let row = match row {
+                Some(row) => row,
+                None => {
+                    let row = draw_row(&data, avatar.as_ref());
+                    store(
+                        &ROW_CACHE,
+                        (guild_id, data.member_id),
+                        fingerprint,
+                        row.clone(),
+                    );
+                    row
+                }
+            };
+            imageops::overlay(
+                &mut card,
+                &row,
+                8,
+                8 + index as i64 * (ROW_HEIGHT as i64 + 8),
+            );
+        }
+        encode(&card)
+    })
+    .await
+    .ok()?
+}
+
+fn draw_row(data: &CardData, avatar: Option<&DynamicImage>) -> RgbaImage {
+    let mut row = RgbaImage::from_pixel(ROW_WIDTH, ROW_HEIGHT, ROW_BACKGROUND);
+    let rank = match data.rank {
+        Some(rank) => format!("#{}", rank),
+        None => "-".to_string(),
+    };
+    draw_text(&mut row, &rank, 12, 18, 22.0, TEXT);
+    if let Some(avatar) = avatar {
+        paste(&mut row, avatar, 72, 8, 48);
+    }
+    let name = match &data.minecraft {
+        Some(minecraft) if minecraft.username != data.name => {
+            format!("{} ({})", data.name, minecraft.username)
+        }
+        _ => data.name.clone(),
+    };
+    draw_text(&mut row, &name, 132, 18, 22.0, TEXT);
+    let messages = format!("{} messages", data.messages);
+    let width = text_width(&messages, 18.0);
+    draw_text(
+        &mut row,
+        &messages,
+        ROW_WIDTH as i32 - 16 - width,
+        21,
+        18.0,
+        SUBTEXT,
+    );
+    row
+}
+
+///Picture of the head of the Minecraft skin.
+pub fn head_url(username: &str) -> String {
+    format!("https://mc-heads.net/avatar/{}/64", username)
+}
+
+async fn fetch_image(url: &str) -> Option<DynamicImage> {
+    if let Ok(images) = IMAGE_CACHE.lock() {
+        if let Some(image) = images.get(url) {
+            return Some(image.clone());
+        }
+    }
+    let bytes = http::send(HTTP_CLIENT.get(url))
+        .await
+        .ok()?
+        .bytes()
+        .await
+        .ok()?;
+    let image = task::spawn_blocking(move || image::load_from_memory(&bytes).ok())
+        .await
+        .ok()??;
+    if let Ok(mut images) = IMAGE_CACHE.lock() {
+        if images.len() >= MAX_CACHED {
+            images.clear();
+        }
+        images.insert(url.to_string(), image.clone());
+    }
+    Some(image)
+}
+
+fn cached<T: Clone>(cache: &RenderCache<T>, key: (u64, u64), fingerprint: &str) -> Option<T> {
+    let cache = cache.lock().ok()?;
+    match cache.get(&key) {
+        Some((stored, value)) if stored == fingerprint => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn store<T>(cache: &RenderCache<T>, key: (u64, u64), fingerprint: String, value: T) {
+    if let Ok(mut cache) = cache.lock() {
+        if cache.len() >= MAX_CACHED {
+            cache.clear();
+        }
+        cache.insert(key, (fingerprint, value));
+    }
+}
+
+fn encode(image: &RgbaImage) -> Option<Vec<u8>> {
+    let mut png = Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(image.clone())
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .ok()?;
+    Some(png.into_inner())
+}
+
+fn paste(target: &mut RgbaImage, source: &DynamicImage, x: i64, y: i64, size: u32) {
+    let resized = imageops::resize(&source.to_rgba8(), size, size, FilterType::Triangle);
+    imageops::overlay(target, &resized, x, y);
+}
+
+fn fill_rect(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for px in x..(x + width).min(image.width()) {
+        for py in y..(y + height).min(image.height()) {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn text_width(text: &str, size: f32) -> i32 {
+    let scale = Scale::uniform(size);
+    FONT.layout(text, scale, point(0.0, 0.0))
+        .last()
+        .map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+        .unwrap_or(0.0) as i32
+}
+
+///Draws `text` with its top left corner at `x`/`y`.
+fn draw_text(image: &mut RgbaImage, text: &str, x: i32, y: i32, size: f32, color: Rgba<u8>) {
+    let scale = Scale::uniform(size);
+    let ascent = FONT.v_metrics(scale).ascent;
+    for glyph in FONT.layout(text, scale, point(x as f32, y as f32 + ascent)) {
+        let bounds = match glyph.pixel_bounding_box() {
+            Some(bounds) => bounds,
+            None => continue,
+        };
+        glyph.draw(|gx, gy, coverage| {
+            let px = gx as i32 + bounds.min.x;
+            let py = gy as i32 + bounds.min.y;
+            if px < 0 || py < 0 || px as u32 >= image.width() || py as u32 >= image.height() {
+                return;
+            }
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] as f32 * (1.0 - coverage)
+                    + color[channel] as f32 * coverage) as u8;
+            }
+        });
+    }
+}
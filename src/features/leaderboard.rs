@@ -0,0 +1,160 @@
+use redis::{Commands, Connection, RedisResult};
+
+//seconds a filtered ranking is kept, it is built again whenever a page is shown
+const FILTER_CACHE_SECONDS: usize = 60;
+//filter id for everyone that is still on the server
+pub const EVERYONE_FILTER: u64 = 0;
+
+pub struct Entry {
+    pub member_id: u64,
+    pub messages: u64,
+    ///Members with the same amount of messages share a rank, the next rank is skipped (1, 1, 3).
+    pub rank: u64,
+}
+
+///Where a member stands in a ranking.
+pub struct Standing {
+    pub rank: u64,
+    pub messages: u64,
+    ///Messages needed to reach the next higher place, None for the first place.
+    pub gap_above: Option<u64>,
+    ///The member with up to `radius` entries above and below them.
+    pub around: Vec<Entry>,
+}
+
+///Number of members with at least one message in the ranking stored at `key`.
+pub fn member_count(con: &mut Connection, key: &str) -> RedisResult<usize> {
+    con.zcount(key, "(0", "+inf")
+}
+
+///`count` entries starting at the zero based position `start`, highest message count first.
+pub fn range(
+    con: &mut Connection,
+    key: &str,
+    start: usize,
+    count: usize,
+) -> RedisResult<Vec<Entry>> {
+    let members: Vec<(u64, u64)> =
+        con.zrevrangebyscore_limit_withscores(key, "+inf", "(0", start as isize, count as isize)?;
+    let mut entries: Vec<Entry> = Vec::with_capacity(members.len());
+    for (position, (member_id, messages)) in members.into_iter().enumerate() {
+        let rank = match entries.last() {
+            Some(previous) if previous.messages == messages => previous.rank,
+            Some(_) => (start + position + 1) as u64,
+            //the first entry could be tied with members on the page before
+            None => rank_of_score(con, key, messages)?,
+        };
+        entries.push(Entry {
+            member_id,
+            messages,
+            rank,
+        });
+    }
+    Ok(entries)
+}
+
+///The standing of the member, None if they don't have any messages.
+pub fn standing(
+    con: &mut Connection,
+    key: &str,
+    member_id: u64,
+    radius: usize,
+) -> RedisResult<Option<Standing>> {
+    let messages: Option<u64> = con.zscore(key, member_id)?;
+    let messages = match messages {
+        Some(messages) if messages > 0 => messages,
+        _ => return Ok(None),
+    };
+    let position: Option<usize> = con.zrevrank(key, member_id)?;
+    let position = match position {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    //the lowest count that is still higher than the one of the member
+    let above: Vec<(u64, u64)> =
+        con.zrangebyscore_limit_withscores(key, format!("({}", messages), "+inf", 0, 1)?;
+    let start = position.saturating_sub(radius);
+    let around = range(con, key, start, position - start + radius + 1)?;
+    Ok(Some(Standing {
+        rank: rank_of_score(con, key, messages)?,
+        messages,
+        gap_above: above.first().map(|(_, higher)| higher - messages),
+        around,
+    }))
+}
+
+///Key of the ranking the member is placed in, the same one the leaderboard shows by default:
+///only the `members` still on the server, or everyone for a member that left, like with `departed`.
+pub fn ranking_for(
+    con: &mut Connection,
+    key: &str,
+    member_id: u64,
+    members: &[u64],
+) -> RedisResult<String> {
+    if members.contains(&member_id) {
+        filtered(con, key, EVERYONE_FILTER, members)
+    } else {
+        Ok(key.to_string())
+    }
+}
+
+///Key of a sorted set with only the `members` of the ranking at `key`, e.g. the ones with a certain role.
+///It is stored next to the ranking for a short time, `filter_id` tells different filters apart.
+pub fn filtered(
+    con: &mut Connection,
+    key: &str,
+    filter_id: u64,
+    members: &[u64],
+) -> RedisResult<String> {
+    let filtered = format!("{}:filter:{}", key, filter_id);
+    let allowed = format!("{}:members", filtered);
+    let mut pipe = redis::pipe();
+    pipe.atomic().del(&allowed).ignore();
+    if !members.is_empty() {
+        pipe.sadd(&allowed, members).ignore();
+    }
+    //the set contributes nothing to the scores, it only decides who is kept
+    pipe.cmd("ZINTERSTORE")
+        .arg(&filtered)
+        .arg(2)
+        .arg(key)
+        .arg(&allowed)
+        .arg("WEIGHTS")
+        .arg(1)
+        .arg(0)
+        .ignore()
+        .expire(&filtered, FILTER_CACHE_SECONDS)
+        .ignore()
+        .del(&allowed)
+        .ignore()
+        .query::<()>(con)?;
+    Ok(filtered)
+}
+
+fn rank_of_score(con: &mut Connection, key: &str, messages: u64) -> RedisResult<u64> {
+    let higher: u64 = con.zcount(key, format!("({}", messages), "+inf")?;
+    Ok(higher + 1)
+}
+
+///Converts message counts that were stored in hashes by older versions into sorted sets.
+///Has to run before any message is counted since the sorted set commands fail on hashes.
+pub fn migrate_hashes(con: &mut Connection) -> RedisResult<()> {
+    let keys: Vec<String> = con.scan_match("verifybot:messages:*")?.collect();
+    for key in keys {
+        let key_type: String = redis::cmd("TYPE").arg(&key).query(con)?;
+        if key_type != "hash" {
+            continue;
+        }
+        let counts: Vec<(u64, i64)> = con.hgetall(&key)?;
+        let temporary = format!("{}:migration", key);
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&temporary).ignore();
+        for (member_id, messages) in counts {
+            pipe.zadd(&temporary, member_id, messages).ignore();
+        }
+        pipe.rename(&temporary, &key).ignore();
+        pipe.query::<()>(con)?;
+        println!("Migrated {} to a sorted set", key);
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+///Levels are based on messages, reaching level `n` takes `5 * n * (n + 1)` messages in total.
+pub struct Level {
+    pub level: u64,
+    ///Messages since reaching the current level.
+    pub progress: u64,
+    ///Messages between the current and the next level.
+    pub needed: u64,
+}
+
+pub fn level_for(messages: u64) -> Level {
+    let mut level = 0;
+    while messages_for(level + 1) <= messages {
+        level += 1;
+    }
+    Level {
+        level,
+        progress: messages - messages_for(level),
+        needed: messages_for(level + 1) - messages_for(level),
+    }
+}
+
+fn messages_for(level: u64) -> u64 {
+    5 * level * (level + 1)
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+use redis::{Commands, Connection, RedisResult};
+
+///The Minecraft account a member verified with.
+pub struct Link {
+    ///Without dashes, as the Mojang API returns it.
+    pub uuid: String,
+    ///Username at the time of the verification.
+    pub username: String,
+    ///Unix time of the verification.
+    pub verified_at: u64,
+    ///Name of the rank role, None for players without a rank.
+    pub rank: Option<String>,
+}
+
+impl Link {
+    ///E.g. `2021-06-01`.
+    pub fn verified_date(&self) -> String {
+        Utc.timestamp(self.verified_at as i64, 0)
+            .format("%Y-%m-%d")
+            .to_string()
+    }
+}
+
+///Stores the link for the guild and as the latest link of the member across all guilds.
+pub fn set_link(
+    con: &mut Connection,
+    guild_id: u64,
+    member_id: u64,
+    link: &Link,
+) -> RedisResult<()> {
+    let mut fields = vec![
+        ("uuid", link.uuid.clone()),
+        ("username", link.username.clone()),
+        ("verified_at", link.verified_at.to_string()),
+        ("guild", guild_id.to_string()),
+    ];
+    if let Some(rank) = &link.rank {
+        fields.push(("rank", rank.clone()));
+    }
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    for key in &[link_key(guild_id, member_id), global_link_key(member_id)] {
+        //fields of the previous link, e.g. the rank, must not survive
+        pipe.del(key).ignore().hset_multiple(key, &fields).ignore();
+    }
+    pipe.sadd(members_key(guild_id), member_id)
+        .ignore()
+        .query(con)
+}
+
+pub fn get_link(con: &mut Connection, guild_id: u64, member_id: u64) -> RedisResult<Option<Link>> {
+    read_link(con, &link_key(guild_id, member_id))
+}
+
+///Members of the guild that have a link.
+pub fn linked_members(con: &mut Connection, guild_id: u64) -> RedisResult<Vec<u64>> {
+    con.smembers(members_key(guild_id))
+}
+
+///The link of the member's latest verification in any guild.
+pub fn global_link(con: &mut Connection, member_id: u64) -> RedisResult<Option<Link>> {
+    read_link(con, &global_link_key(member_id))
+}
+
+///Links of every linked member of the guild, for resolving many members at once.
+pub fn guild_links(con: &mut Connection, guild_id: u64) -> RedisResult<HashMap<u64, Link>> {
+    let member_ids = linked_members(con, guild_id)?;
+    if member_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut pipe = redis::pipe();
+    for member_id in &member_ids {
+        pipe.hgetall(link_key(guild_id, *member_id));
+    }
+    let fields: Vec<HashMap<String, String>> = pipe.query(con)?;
+    Ok(member_ids
+        .into_iter()
+        .zip(fields)
+        .filter_map(|(member_id, fields)| Some((member_id, parse_link(fields)?)))
+        .collect())
+}
+
+fn read_link(con: &mut Connection, key: &str) -> RedisResult<Option<Link>> {
+    let fields: HashMap<String, String> = con.hgetall(key)?;
+    Ok(parse_link(fields))
+}
+
+fn parse_link(mut fields: HashMap<String, String>) -> Option<Link> {
+    match (
+        fields.remove("uuid"),
+        fields.remove("username"),
+        fields.get("verified_at").and_then(|time| time.parse().ok()),
+    ) {
+        (Some(uuid), Some(username), Some(verified_at)) => Some(Link {
+            uuid,
+            username,
+            verified_at,
+            rank: fields.remove("rank"),
+        }),
+        _ => None,
+    }
+}
+
+fn link_key(guild_id: u64, member_id: u64) -> String {
+    format!("verifybot:link:{}:{}", guild_id, member_id)
+}
+
+fn global_link_key(member_id: u64) -> String {
+    format!("verifybot:link:{}", member_id)
+}
+
+///Ids of the members with a link in the guild.
+fn members_key(guild_id: u64) -> String {
+    format!("verifybot:links:{}", guild_id)
+}
@@ -0,0 +1,46 @@
+use redis::{Commands, Connection, RedisResult};
+
+use crate::features::leaderboard::{self, Standing};
+use crate::features::message_counting::messages_key;
+use crate::features::periods::{period_key, Period};
+use crate::features::streaks::{self, Streak};
+use crate::features::voice_tracking;
+
+///Everything stored about the activity of one member.
+pub struct MemberStats {
+    ///All time messages.
+    pub messages: u64,
+    ///Where the member stands in each requested period, None if they have no messages in it.
+    pub standings: Vec<(Period, Option<Standing>)>,
+    pub streak: Streak,
+    ///Seconds spent in voice channels.
+    pub voice: u64,
+}
+
+///Reads the stats of the member, `radius` is the number of members shown around them in each standing.
+///`members` are the current members of the guild, standings are among them like on the leaderboard.
+pub fn read(
+    con: &mut Connection,
+    guild_id: u64,
+    member_id: u64,
+    periods: &[Period],
+    radius: usize,
+    members: &[u64],
+) -> RedisResult<MemberStats> {
+    let messages: Option<i64> = con.zscore(messages_key(guild_id), member_id)?;
+    let mut standings = Vec::with_capacity(periods.len());
+    for period in periods {
+        let key = period_key(con, guild_id, *period)?;
+        let key = leaderboard::ranking_for(con, &key, member_id, members)?;
+        standings.push((
+            *period,
+            leaderboard::standing(con, &key, member_id, radius)?,
+        ));
+    }
+    Ok(MemberStats {
+        messages: messages.unwrap_or(0).max(0) as u64,
+        standings,
+        streak: streaks::get_streak(con, guild_id, member_id)?,
+        voice: voice_tracking::get_voice_time(con, guild_id, member_id)?,
+    })
+}
@@ -1,35 +1,282 @@
-use redis::{Commands, ErrorKind, RedisResult};
-use serenity::model::guild::Member;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::REDIS_CLIENT;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Timelike, Utc};
+use lazy_static::lazy_static;
+use redis::{Commands, Connection, Pipeline, RedisResult};
 
-pub async fn handle_messages_redis(member: Member) {
-    let guild_id = member.guild_id.to_string();
-    let member_id = member.user.id.to_string();
+use serenity::model::id::MessageId;
+
+use crate::features::backfill::{self, Gap};
+use crate::features::{seasons, streaks};
+use crate::{COUNTED_MESSAGE_WINDOW, REDIS_CLIENT};
+
+//days the per day activity buckets are kept for
+pub const ACTIVITY_RETENTION_DAYS: i64 = 90;
+//days the per hour member buckets are kept for, enough for the window of a monthly post
+const MEMBER_HOURLY_RETENTION_DAYS: i64 = 35;
+
+//keeps the lowest/highest timestamp per member, backfilled messages arrive out of order
+const KEEP_EARLIEST: &str = "local current = redis.call('ZSCORE', KEYS[1], ARGV[1]) \
+if not current or tonumber(ARGV[2]) < tonumber(current) then redis.call('ZADD', KEYS[1], ARGV[2], ARGV[1]) end";
+const KEEP_LATEST: &str = "local current = redis.call('ZSCORE', KEYS[1], ARGV[1]) \
+if not current or tonumber(ARGV[2]) > tonumber(current) then redis.call('ZADD', KEYS[1], ARGV[2], ARGV[1]) end";
+
+lazy_static! {
+    //channels that had a message counted since the bot started
+    static ref SEEN_CHANNELS: Mutex<HashSet<u64>> = Mutex::new(HashSet::new());
+}
+
+///Everything the counters need to know about one message.
+pub struct CountedMessage {
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub member_id: u64,
+    pub sent_at: DateTime<Utc>,
+}
+
+pub async fn handle_messages_redis(message: CountedMessage, message_id: u64) {
+    let CountedMessage {
+        guild_id,
+        channel_id,
+        member_id,
+        ..
+    } = message;
     let con = REDIS_CLIENT.get_connection();
     if con.is_err() {
         println!("Error while opening redis connection");
         return;
     }
     let mut con = con.unwrap();
-    let key = format!("verifybot:messages:{}", guild_id);
-    //get current messages for this user
-    let current_value: RedisResult<isize> = con.hget(&key, &member_id);
-    let current_messages: i64;
-    if current_value.is_err() {
-        //TypeError means its probably nil so it can be set to 0, return on every other error
-        if !(current_value.err().unwrap().kind() == ErrorKind::TypeError) {
-            return;
-        } else {
-            current_messages = 0;
+    track_channel_gap(&mut con, guild_id, channel_id, message_id);
+    let now = unix_now();
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    count_message(&mut pipe, &message);
+    remember_counted(&mut pipe, guild_id, message_id, member_id, now);
+    let result: RedisResult<()> = pipe
+        .hset(backfill::channel_last_key(guild_id), channel_id, message_id)
+        .ignore()
+        .query(&mut con);
+    if let Err(err) = result {
+        println!("Error while counting message: {}", err);
+        return;
+    }
+    prune_counted_messages(&mut con, guild_id, now);
+}
+
+///Adds one message to the pipeline. Live counting and the backfill both go through this.
+pub fn count_message(pipe: &mut Pipeline, message: &CountedMessage) {
+    pipe.zincr(messages_key(message.guild_id), message.member_id, 1)
+        .ignore();
+    add_to_activity(pipe, message, 1);
+    seasons::add_to_season(pipe, message, 1);
+    streaks::record_activity(pipe, message.guild_id, message.member_id, message.sent_at);
+    let timestamp = message.sent_at.timestamp();
+    for (script, key) in &[
+        (KEEP_EARLIEST, first_seen_key(message.guild_id)),
+        (KEEP_LATEST, last_seen_key(message.guild_id)),
+    ] {
+        pipe.cmd("EVAL")
+            .arg(*script)
+            .arg(1)
+            .arg(key)
+            .arg(message.member_id)
+            .arg(timestamp)
+            .ignore();
+    }
+}
+
+///Remembers who sent the message so a deletion within the configured window after `counted_at` can be reversed.
+pub fn remember_counted(
+    pipe: &mut Pipeline,
+    guild_id: u64,
+    message_id: u64,
+    member_id: u64,
+    counted_at: u64,
+) {
+    pipe.zadd(counted_key(guild_id), message_id, counted_at)
+        .ignore()
+        .hset(counted_authors_key(guild_id), message_id, member_id)
+        .ignore();
+}
+
+///Takes one message back out of the counters.
+pub fn uncount_message(pipe: &mut Pipeline, message: &CountedMessage) {
+    pipe.zincr(messages_key(message.guild_id), message.member_id, -1)
+        .ignore();
+    add_to_activity(pipe, message, -1);
+    seasons::add_to_season(pipe, message, -1);
+}
+
+///Updates the per day buckets, which are only written while they are within the retention period.
+fn add_to_activity(pipe: &mut Pipeline, message: &CountedMessage, amount: i64) {
+    let day = message.sent_at.date().naive_utc();
+    let expire_at = day.and_hms(0, 0, 0) + Duration::days(ACTIVITY_RETENTION_DAYS);
+    if expire_at <= Utc::now().naive_utc() {
+        return;
+    }
+    let expire_at = expire_at.timestamp() as usize;
+    let guild_id = message.guild_id;
+    pipe.zincr(daily_key(guild_id, day), message.member_id, amount)
+        .ignore()
+        .expire_at(daily_key(guild_id, day), expire_at)
+        .ignore()
+        .hincr(hourly_key(guild_id, day), message.sent_at.hour(), amount)
+        .ignore()
+        .expire_at(hourly_key(guild_id, day), expire_at)
+        .ignore()
+        .zincr(channels_key(guild_id, day), message.channel_id, amount)
+        .ignore()
+        .expire_at(channels_key(guild_id, day), expire_at)
+        .ignore();
+
+    let hour = day.and_hms(message.sent_at.hour(), 0, 0);
+    let expire_at = hour + Duration::days(MEMBER_HOURLY_RETENTION_DAYS);
+    if expire_at > Utc::now().naive_utc() {
+        pipe.zincr(member_hourly_key(guild_id, hour), message.member_id, amount)
+            .ignore()
+            .expire_at(
+                member_hourly_key(guild_id, hour),
+                expire_at.timestamp() as usize,
+            )
+            .ignore();
+    }
+}
+
+///Reverses the increment for every deleted message that was counted within the configured window.
+pub async fn handle_message_deletion(guild_id: u64, channel_id: u64, message_ids: Vec<u64>) {
+    let con = REDIS_CLIENT.get_connection();
+    if con.is_err() {
+        println!("Error while opening redis connection");
+        return;
+    }
+    let mut con = con.unwrap();
+    let cutoff = unix_now().saturating_sub(*COUNTED_MESSAGE_WINDOW);
+    for message_id in message_ids {
+        let counted_at: RedisResult<Option<u64>> = con.zscore(counted_key(guild_id), message_id);
+        match counted_at {
+            Ok(Some(counted_at)) if counted_at >= cutoff => {}
+            _ => continue,
+        }
+        let author: RedisResult<Option<u64>> = con.hget(counted_authors_key(guild_id), message_id);
+        //only the call that actually removes the entry may decrement, so duplicate events are harmless
+        let removed: RedisResult<u8> = con.zrem(counted_key(guild_id), message_id);
+        let _: RedisResult<()> = con.hdel(counted_authors_key(guild_id), message_id);
+        if let (Ok(Some(member_id)), Ok(1)) = (author, removed) {
+            let mut pipe = redis::pipe();
+            uncount_message(
+                &mut pipe,
+                &CountedMessage {
+                    guild_id,
+                    channel_id,
+                    member_id,
+                    sent_at: MessageId(message_id).created_at(),
+                },
+            );
+            let _: RedisResult<()> = pipe.query(&mut con);
         }
-    } else {
-        current_messages = current_value.unwrap() as i64;
-    }
-    //save new message count to db
-    redis::cmd("HSET")
-        .arg(&key)
-        .arg(&member_id)
-        .arg(current_messages + 1)
-        .execute(&mut con);
+    }
+}
+
+///The first message in a channel after a restart marks everything since the last counted one as a gap for the backfill.
+fn track_channel_gap(con: &mut Connection, guild_id: u64, channel_id: u64, message_id: u64) {
+    let first_this_session = match SEEN_CHANNELS.lock() {
+        Ok(mut seen) => seen.insert(channel_id),
+        Err(_) => false,
+    };
+    if !first_this_session {
+        return;
+    }
+    let last: RedisResult<Option<u64>> = con.hget(backfill::channel_last_key(guild_id), channel_id);
+    let after = match last {
+        Ok(Some(last)) => last,
+        Ok(None) => match backfill::history_start(con, guild_id, message_id) {
+            Ok(after) => after,
+            Err(_) => return,
+        },
+        Err(_) => return,
+    };
+    backfill::register_gap(
+        con,
+        guild_id,
+        &Gap {
+            channel_id,
+            after,
+            before: message_id,
+        },
+    );
+}
+
+///Drops every remembered message that is older than the configured window.
+fn prune_counted_messages(con: &mut Connection, guild_id: u64, now: u64) {
+    let cutoff = now.saturating_sub(*COUNTED_MESSAGE_WINDOW);
+    let expired: RedisResult<Vec<u64>> =
+        con.zrangebyscore(counted_key(guild_id), "-inf", format!("({}", cutoff));
+    let expired = match expired {
+        Ok(expired) if !expired.is_empty() => expired,
+        _ => return,
+    };
+    let _: RedisResult<()> = redis::pipe()
+        .atomic()
+        .zrem(counted_key(guild_id), expired.clone())
+        .ignore()
+        .hdel(counted_authors_key(guild_id), expired)
+        .ignore()
+        .query(con);
+}
+
+///All time messages per member, a sorted set so ranks can be looked up directly.
+pub fn messages_key(guild_id: u64) -> String {
+    format!("verifybot:messages:{}", guild_id)
+}
+
+///Messages per member on one day.
+pub fn daily_key(guild_id: u64, day: NaiveDate) -> String {
+    format!("verifybot:daily:{}:{}", guild_id, day.format("%Y-%m-%d"))
+}
+
+///Messages per hour (UTC) on one day.
+pub fn hourly_key(guild_id: u64, day: NaiveDate) -> String {
+    format!("verifybot:hourly:{}:{}", guild_id, day.format("%Y-%m-%d"))
+}
+
+///Messages per member in one hour (UTC), for windows that don't start at midnight UTC.
+pub fn member_hourly_key(guild_id: u64, hour: NaiveDateTime) -> String {
+    format!(
+        "verifybot:member_hourly:{}:{}",
+        guild_id,
+        hour.format("%Y-%m-%dT%H")
+    )
+}
+
+///Messages per channel on one day.
+pub fn channels_key(guild_id: u64, day: NaiveDate) -> String {
+    format!("verifybot:channels:{}:{}", guild_id, day.format("%Y-%m-%d"))
+}
+
+///Unix timestamp of the first counted message per member.
+pub fn first_seen_key(guild_id: u64) -> String {
+    format!("verifybot:first_seen:{}", guild_id)
+}
+
+///Unix timestamp of the latest counted message per member.
+pub fn last_seen_key(guild_id: u64) -> String {
+    format!("verifybot:last_seen:{}", guild_id)
+}
+
+fn counted_key(guild_id: u64) -> String {
+    format!("verifybot:counted:{}", guild_id)
+}
+
+fn counted_authors_key(guild_id: u64) -> String {
+    format!("verifybot:counted_authors:{}", guild_id)
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
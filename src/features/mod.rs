@@ -1 +1,14 @@
 pub mod message_counting;
+pub mod backfill;
+pub mod activity;
+pub mod streaks;
+pub mod voice_tracking;
+pub mod leaderboard;
+pub mod periods;
+pub mod levels;
+pub mod cards;
+pub mod schedule;
+pub mod seasons;
+pub mod member_stats;
+pub mod links;
+pub mod sync;
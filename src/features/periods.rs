@@ -0,0 +1,149 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Timelike, Utc};
+use redis::{Commands, Connection, RedisResult};
+
+use crate::features::message_counting::{daily_key, member_hourly_key, messages_key};
+use crate::features::seasons::season_key;
+
+//seconds a combined period ranking is reused before it is built again
+const PERIOD_CACHE_SECONDS: usize = 60;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Period {
+    AllTime,
+    ///The last 7 days including today (UTC).
+    Week,
+    ///The last 30 days including today (UTC).
+    Month,
+    ///Since the current season was started.
+    Season,
+}
+
+impl Period {
+    pub fn from_arg(arg: &str) -> Option<Period> {
+        match arg {
+            "all" | "alltime" => Some(Period::AllTime),
+            "week" | "weekly" => Some(Period::Week),
+            "month" | "monthly" => Some(Period::Month),
+            "season" => Some(Period::Season),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Period::AllTime => "All time",
+            Period::Week => "Last 7 days",
+            Period::Month => "Last 30 days",
+            Period::Season => "Current season",
+        }
+    }
+
+    fn days(&self) -> i64 {
+        match self {
+            Period::AllTime | Period::Season => 0,
+            Period::Week => 7,
+            Period::Month => 30,
+        }
+    }
+}
+
+///Key of a sorted set with the messages per member in the period, combined from the per day buckets if needed.
+pub fn period_key(con: &mut Connection, guild_id: u64, period: Period) -> RedisResult<String> {
+    match period {
+        Period::AllTime => return Ok(messages_key(guild_id)),
+        Period::Season => return Ok(season_key(guild_id)),
+        _ => {}
+    }
+    let key = format!("verifybot:period:{}:{}", guild_id, period.days());
+    let cached: bool = con.exists(&key)?;
+    if cached {
+        return Ok(key);
+    }
+    let today = Utc::today().naive_utc();
+    let days: Vec<String> = (0..period.days())
+        .map(|offset| daily_key(guild_id, today - Duration::days(offset)))
+        .collect();
+    redis::pipe()
+        .atomic()
+        .zunionstore(&key, &days.iter().collect::<Vec<&String>>())
+        .ignore()
+        .expire(&key, PERIOD_CACHE_SECONDS)
+        .ignore()
+        .query::<()>(con)?;
+    Ok(key)
+}
+
+///Key of a sorted set with the messages per member from `start` until `end`, accurate to the hour.
+pub fn window_key(
+    con: &mut Connection,
+    guild_id: u64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> RedisResult<String> {
+    let key = format!(
+        "verifybot:window:{}:{}:{}",
+        guild_id,
+        start.timestamp(),
+        end.timestamp()
+    );
+    let buckets = window_buckets(guild_id, start.naive_utc(), end.naive_utc());
+    let mut pipe = redis::pipe();
+    pipe.atomic().del(&key).ignore();
+    if !buckets.is_empty() {
+        pipe.zunionstore(&key, &buckets.iter().collect::<Vec<&String>>())
+            .ignore()
+            .expire(&key, PERIOD_CACHE_SECONDS)
+            .ignore();
+    }
+    pipe.query::<()>(con)?;
+    Ok(key)
+}
+
+///Whole UTC days are taken from the per day buckets, the hours around them from the per hour ones.
+fn window_buckets(guild_id: u64, start: NaiveDateTime, end: NaiveDateTime) -> Vec<String> {
+    let mut buckets = Vec::new();
+    let mut hour = start.date().and_hms(start.hour(), 0, 0);
+    while hour < end {
+        if hour.hour() == 0 && hour + Duration::days(1) <= end {
+            buckets.push(daily_key(guild_id, hour.date()));
+            hour += Duration::days(1);
+        } else {
+            buckets.push(member_hourly_key(guild_id, hour));
+            hour += Duration::hours(1);
+        }
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn window_uses_days_where_they_fit() {
+        let start = NaiveDate::from_ymd(2021, 3, 22).and_hms(17, 0, 0);
+        let end = NaiveDate::from_ymd(2021, 3, 29).and_hms(16, 0, 0);
+        let buckets = window_buckets(1, start, end);
+        //17:00 to midnight, 6 whole days, midnight to 16:00
+        assert_eq!(buckets.len(), 7 + 6 + 16);
+        assert_eq!(buckets[0], "verifybot:member_hourly:1:2021-03-22T17");
+        assert_eq!(buckets[7], "verifybot:daily:1:2021-03-23");
+        assert_eq!(buckets[12], "verifybot:daily:1:2021-03-28");
+        assert_eq!(buckets[28], "verifybot:member_hourly:1:2021-03-29T15");
+    }
+
+    #[test]
+    fn window_starting_within_an_hour_includes_that_hour() {
+        let start = NaiveDate::from_ymd(2021, 3, 22).and_hms(12, 30, 0);
+        let end = NaiveDate::from_ymd(2021, 3, 22).and_hms(14, 30, 0);
+        assert_eq!(
+            window_buckets(1, start, end),
+            vec![
+                "verifybot:member_hourly:1:2021-03-22T12",
+                "verifybot:member_hourly:1:2021-03-22T13",
+                "verifybot:member_hourly:1:2021-03-22T14",
+            ]
+        );
+    }
+}
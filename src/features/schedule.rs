@@ -0,0 +1,477 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use redis::{Commands, Connection, RedisResult};
+use serenity::client::Context;
+use serenity::model::id::{ChannelId, GuildId};
+use string_builder::Builder;
+
+use crate::commands::members::member_labels;
+use crate::features::leaderboard::{self, Entry};
+use crate::features::message_counting::unix_now;
+use crate::features::periods::{window_key, Period};
+use crate::REDIS_CLIENT;
+
+//how often the scheduler looks for posts that are due
+const CHECK_INTERVAL: StdDuration = StdDuration::from_secs(30);
+//members shown in a scheduled post
+const POST_SIZE: usize = 10;
+//members shown in the top movers section
+const MOVERS: usize = 5;
+//monthly posts are limited to days every month has
+pub const LAST_MONTHLY_DAY: u32 = 28;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+///A leaderboard that gets posted to a channel regularly.
+pub struct Schedule {
+    pub channel_id: u64,
+    ///Either `Period::Week` or `Period::Month`.
+    pub period: Period,
+    ///Days since monday for weekly posts, day of the month for monthly posts.
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Schedule {
+    ///E.g. `every Monday at 18:00`.
+    pub fn describe(&self) -> String {
+        let day = match self.period {
+            Period::Month => format!("on day {} of every month", self.day),
+            _ => format!("every {}", weekday_name(self.day)),
+        };
+        format!("{} at {:02}:{:02}", day, self.hour, self.minute)
+    }
+}
+
+///The timezone schedules of the guild are in, UTC if none is set.
+pub fn guild_timezone(con: &mut Connection, guild_id: u64) -> RedisResult<Tz> {
+    let timezone: Option<String> = con.hget(config_key(guild_id), "timezone")?;
+    Ok(timezone
+        .and_then(|timezone| Tz::from_str(&timezone).ok())
+        .unwrap_or(Tz::UTC))
+}
+
+pub fn set_guild_timezone(con: &mut Connection, guild_id: u64, timezone: Tz) -> RedisResult<()> {
+    con.hset(config_key(guild_id), "timezone", timezone.name())
+}
+
+pub fn get_schedule(con: &mut Connection, guild_id: u64) -> RedisResult<Option<Schedule>> {
+    let fields: HashMap<String, String> = con.hgetall(schedule_key(guild_id))?;
+    let number = |name: &str| fields.get(name).and_then(|value| value.parse().ok());
+    let period = fields
+        .get("period")
+        .and_then(|period| Period::from_arg(period));
+    Ok(
+        match (
+            number("channel"),
+            period,
+            number("day"),
+            number("hour"),
+            number("minute"),
+        ) {
+            (Some(channel_id), Some(period), Some(day), Some(hour), Some(minute)) => {
+                Some(Schedule {
+                    channel_id,
+                    period,
+                    day: day as u32,
+                    hour: hour as u32,
+                    minute: minute as u32,
+                })
+            }
+            _ => None,
+        },
+    )
+}
+
+///Stores the schedule, replacing the previous one, and returns when it is posted next.
+pub fn set_schedule(
+    con: &mut Connection,
+    guild_id: u64,
+    schedule: &Schedule,
+) -> RedisResult<Option<DateTime<Tz>>> {
+    let period = match schedule.period {
+        Period::Month => "month",
+        _ => "week",
+    };
+    redis::pipe()
+        .atomic()
+        .del(schedule_key(guild_id))
+        .ignore()
+        .hset_multiple(
+            schedule_key(guild_id),
+            &[
+                ("channel", schedule.channel_id.to_string()),
+                ("period", period.to_string()),
+                ("day", schedule.day.to_string()),
+                ("hour", schedule.hour.to_string()),
+                ("minute", schedule.minute.to_string()),
+            ],
+        )
+        .ignore()
+        //movers of a different period can't be compared with the old ranking
+        .del(snapshot_key(guild_id))
+        .ignore()
+        .query::<()>(con)?;
+    reschedule(con, guild_id)
+}
+
+pub fn remove_schedule(con: &mut Connection, guild_id: u64) -> RedisResult<()> {
+    redis::pipe()
+        .atomic()
+        .del(schedule_key(guild_id))
+        .ignore()
+        .del(snapshot_key(guild_id))
+        .ignore()
+        .zrem(due_key(), guild_id)
+        .ignore()
+        .query(con)
+}
+
+///Calculates the next post of the guild from now on, needed whenever the schedule or the timezone changes.
+pub fn reschedule(con: &mut Connection, guild_id: u64) -> RedisResult<Option<DateTime<Tz>>> {
+    let schedule = match get_schedule(con, guild_id)? {
+        Some(schedule) => schedule,
+        None => {
+            let _: () = con.zrem(due_key(), guild_id)?;
+            return Ok(None);
+        }
+    };
+    let timezone = guild_timezone(con, guild_id)?;
+    let next = next_run(&schedule, timezone, Utc::now());
+    match next {
+        Some(next) => con.zadd(due_key(), guild_id, next.timestamp())?,
+        None => con.zrem(due_key(), guild_id)?,
+    }
+    Ok(next)
+}
+
+///Posts scheduled leaderboards once they are due. The due times are stored, so posts that
+///were missed while the bot was offline are sent once after it starts again.
+pub async fn run(ctx: Context) {
+    //ready fires again after reconnects
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    loop {
+        let due: RedisResult<Vec<u64>> = REDIS_CLIENT
+            .get_connection()
+            .and_then(|mut con| con.zrangebyscore(due_key(), "-inf", unix_now()));
+        match due {
+            Ok(due) => {
+                for guild_id in due {
+                    post(&ctx, guild_id).await;
+                }
+            }
+            Err(err) => println!("Error while checking scheduled posts: {}", err),
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn post(ctx: &Context, guild_id: u64) {
+    let con = REDIS_CLIENT.get_connection();
+    if con.is_err() {
+        println!("Error while opening redis connection");
+        return;
+    }
+    let mut con = con.unwrap();
+    //moved on before posting so a post that fails isn't retried every few seconds
+    if let Err(err) = reschedule(&mut con, guild_id) {
+        println!(
+            "Error while scheduling the next post for {}: {}",
+            guild_id, err
+        );
+        return;
+    }
+    let schedule = match get_schedule(&mut con, guild_id) {
+        Ok(Some(schedule)) => schedule,
+        Ok(None) => return,
+        Err(err) => {
+            println!("Error while reading the schedule of {}: {}", guild_id, err);
+            return;
+        }
+    };
+    let timezone = match guild_timezone(&mut con, guild_id) {
+        Ok(timezone) => timezone,
+        Err(err) => {
+            println!("Error while reading the timezone of {}: {}", guild_id, err);
+            return;
+        }
+    };
+    let (start, end) = match window(&schedule, timezone, Utc::now()) {
+        Some(window) => window,
+        None => return,
+    };
+    //members that left are left out like on the leaderboard
+    let labels = match member_labels(ctx, GuildId(guild_id)).await {
+        Some(labels) => labels,
+        None => {
+            println!("Error while reading the members of {}", guild_id);
+            return;
+        }
+    };
+    let content = post_content(&mut con, guild_id, start, end, &labels);
+    if let Err(err) = content {
+        println!(
+            "Error while building the scheduled post for {}: {}",
+            guild_id, err
+        );
+        return;
+    }
+    let (top, movers) = content.unwrap();
+    let title = match schedule.period {
+        Period::Month => "Monthly message leaderboard",
+        _ => "Weekly message leaderboard",
+    };
+    let result = ChannelId(schedule.channel_id)
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!(
+                    "{} ({} to {})",
+                    title,
+                    start.format("%-d %b %H:%M"),
+                    end.format("%-d %b %H:%M %Z")
+                ))
+                .description(top)
+                .field("Top movers", movers, false)
+            })
+        })
+        .await;
+    if let Err(err) = result {
+        println!(
+            "Error while sending the scheduled post for {}: {}",
+            guild_id, err
+        );
+    }
+}
+
+///The top of the ranking and the members that climbed the most places since the last post.
+fn post_content(
+    con: &mut Connection,
+    guild_id: u64,
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+    labels: &HashMap<u64, String>,
+) -> RedisResult<(String, String)> {
+    let key = window_key(
+        con,
+        guild_id,
+        start.with_timezone(&Utc),
+        end.with_timezone(&Utc),
+    )?;
+    let members: Vec<u64> = labels.keys().copied().collect();
+    let key = leaderboard::filtered(con, &key, leaderboard::EVERYONE_FILTER, &members)?;
+    let members = leaderboard::member_count(con, &key)?;
+    let entries = leaderboard::range(con, &key, 0, members)?;
+
+    let mut top = Builder::default();
+    for entry in entries.iter().take(POST_SIZE) {
+        top.append(format!(
+            "{} has {} messages and is Place {}\n",
+            labels[&entry.member_id], entry.messages, entry.rank
+        ));
+    }
+    if entries.is_empty() {
+        top.append("Nobody sent any messages in this period.");
+    }
+
+    let previous: HashMap<u64, u64> = con.zrange_withscores(snapshot_key(guild_id), 0, -1)?;
+    let mut climbed: Vec<(&Entry, u64)> = entries
+        .iter()
+        .filter_map(|entry| match previous.get(&entry.member_id) {
+            Some(rank) if *rank > entry.rank => Some((entry, rank - entry.rank)),
+            _ => None,
+        })
+        .collect();
+    climbed.sort_by_key(|(_, places)| Reverse(*places));
+    let mut movers = Builder::default();
+    for (entry, places) in climbed.iter().take(MOVERS) {
+        movers.append(format!(
+            "{} climbed {} places to Place {}\n",
+            labels[&entry.member_id], places, entry.rank
+        ));
+    }
+    if previous.is_empty() {
+        movers.append("Shown from the next post on.");
+    } else if climbed.is_empty() {
+        movers.append("Nobody climbed since the last post.");
+    }
+
+    //remember the ranks for the movers of the next post
+    let mut pipe = redis::pipe();
+    pipe.atomic().del(snapshot_key(guild_id)).ignore();
+    for entry in &entries {
+        pipe.zadd(snapshot_key(guild_id), entry.member_id, entry.rank)
+            .ignore();
+    }
+    pipe.query::<()>(con)?;
+
+    Ok((top.string().unwrap(), movers.string().unwrap()))
+}
+
+///The first time after `after` the schedule matches in the timezone.
+fn next_run(schedule: &Schedule, timezone: Tz, after: DateTime<Utc>) -> Option<DateTime<Tz>> {
+    let today = after.with_timezone(&timezone).date().naive_local();
+    //every matching day is at most a month away
+    (0..=62)
+        .filter_map(|offset| run_on(schedule, timezone, today + Duration::days(offset)))
+        .find(|time| *time > after)
+}
+
+///The last time at or before `before` the schedule matched in the timezone.
+fn previous_run(schedule: &Schedule, timezone: Tz, before: DateTime<Utc>) -> Option<DateTime<Tz>> {
+    let today = before.with_timezone(&timezone).date().naive_local();
+    (0..=62)
+        .filter_map(|offset| run_on(schedule, timezone, today - Duration::days(offset)))
+        .find(|time| *time <= before)
+}
+
+///The time the schedule runs on the local date, None if it doesn't run that day.
+fn run_on(schedule: &Schedule, timezone: Tz, date: NaiveDate) -> Option<DateTime<Tz>> {
+    let matches = match schedule.period {
+        Period::Month => date.day() == schedule.day,
+        _ => date.weekday().num_days_from_monday() == schedule.day,
+    };
+    if !matches {
+        return None;
+    }
+    let time = date.and_hms(schedule.hour, schedule.minute, 0);
+    //times skipped by a daylight saving change are posted when the clock moved on, repeated ones the first time
+    timezone.from_local_datetime(&time).earliest().or_else(|| {
+        timezone
+            .from_local_datetime(&(time + Duration::hours(1)))
+            .earliest()
+    })
+}
+
+///From the post before the one that is due at `now` until the due one, so every post covers
+///the time since the previous one in the timezone of the guild.
+fn window(
+    schedule: &Schedule,
+    timezone: Tz,
+    now: DateTime<Utc>,
+) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+    let end = previous_run(schedule, timezone, now)?;
+    let start = previous_run(
+        schedule,
+        timezone,
+        end.with_timezone(&Utc) - Duration::seconds(1),
+    )?;
+    Some((start, end))
+}
+
+fn weekday_name(day: u32) -> &'static str {
+    match day {
+        0 => "Monday",
+        1 => "Tuesday",
+        2 => "Wednesday",
+        3 => "Thursday",
+        4 => "Friday",
+        5 => "Saturday",
+        _ => "Sunday",
+    }
+}
+
+///Parses `mon`, `monday` etc. into days since monday.
+pub fn parse_weekday(arg: &str) -> Option<u32> {
+    Weekday::from_str(arg)
+        .ok()
+        .map(|weekday| weekday.num_days_from_monday())
+}
+
+fn config_key(guild_id: u64) -> String {
+    format!("verifybot:config:{}", guild_id)
+}
+
+fn schedule_key(guild_id: u64) -> String {
+    format!("verifybot:schedule:{}", guild_id)
+}
+
+///Ranks of the last scheduled post per member.
+fn snapshot_key(guild_id: u64) -> String {
+    format!("verifybot:schedule_ranks:{}", guild_id)
+}
+
+///Unix time of the next post per guild.
+fn due_key() -> String {
+    "verifybot:schedule_due".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    fn weekly(day: u32, hour: u32, minute: u32) -> Schedule {
+        Schedule {
+            channel_id: 0,
+            period: Period::Week,
+            day,
+            hour,
+            minute,
+        }
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.ymd(year, month, day).and_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn next_run_keeps_the_local_time_across_daylight_saving_changes() {
+        //monday 18:00, clocks go forward on sunday 28 march 2021
+        let schedule = weekly(0, 18, 0);
+        let next = next_run(&schedule, Berlin, utc(2021, 3, 22, 18, 0)).unwrap();
+        assert_eq!(next.with_timezone(&Utc), utc(2021, 3, 29, 16, 0));
+        let next = next_run(&schedule, Berlin, utc(2021, 3, 22, 16, 0)).unwrap();
+        assert_eq!(next.with_timezone(&Utc), utc(2021, 3, 22, 17, 0));
+    }
+
+    #[test]
+    fn next_run_posts_skipped_times_after_the_gap() {
+        //sunday 02:30 doesn't exist on 28 march 2021, the clock jumps from 02:00 to 03:00
+        let schedule = weekly(6, 2, 30);
+        let next = next_run(&schedule, Berlin, utc(2021, 3, 27, 12, 0)).unwrap();
+        assert_eq!(next.with_timezone(&Utc), utc(2021, 3, 28, 1, 30));
+    }
+
+    #[test]
+    fn next_run_posts_repeated_times_once() {
+        //sunday 02:30 happens twice on 31 october 2021, at 00:30 and 01:30 UTC
+        let schedule = weekly(6, 2, 30);
+        let next = next_run(&schedule, Berlin, utc(2021, 10, 30, 12, 0)).unwrap();
+        assert_eq!(next.with_timezone(&Utc), utc(2021, 10, 31, 0, 30));
+        let next = next_run(&schedule, Berlin, utc(2021, 10, 31, 0, 45)).unwrap();
+        assert_eq!(next.with_timezone(&Utc), utc(2021, 11, 7, 1, 30));
+    }
+
+    #[test]
+    fn weekly_window_spans_the_local_week() {
+        let schedule = weekly(0, 18, 0);
+        //posted a bit late, the week that lost an hour to daylight saving
+        let (start, end) = window(&schedule, Berlin, utc(2021, 3, 29, 16, 5)).unwrap();
+        assert_eq!(start.with_timezone(&Utc), utc(2021, 3, 22, 17, 0));
+        assert_eq!(end.with_timezone(&Utc), utc(2021, 3, 29, 16, 0));
+        assert_eq!(end - start, Duration::hours(7 * 24 - 1));
+    }
+
+    #[test]
+    fn monthly_window_spans_the_local_month() {
+        let schedule = Schedule {
+            period: Period::Month,
+            ..weekly(1, 0, 0)
+        };
+        let (start, end) = window(&schedule, Berlin, utc(2021, 3, 31, 22, 0)).unwrap();
+        assert_eq!(start.with_timezone(&Utc), utc(2021, 2, 28, 23, 0));
+        assert_eq!(end.with_timezone(&Utc), utc(2021, 3, 31, 22, 0));
+        //before the post of april it is still the window of march
+        let (start, _) = window(&schedule, Berlin, utc(2021, 3, 31, 21, 59)).unwrap();
+        assert_eq!(start.with_timezone(&Utc), utc(2021, 1, 31, 23, 0));
+    }
+}
@@ -0,0 +1,116 @@
+use chrono::{TimeZone, Utc};
+use redis::{Connection, Pipeline, RedisResult};
+
+use crate::features::message_counting::{unix_now, CountedMessage};
+
+//only messages sent after the current season started count towards it,
+//so backfilled history and deletions of older messages leave it alone
+//KEYS: season info, season ranking; ARGV: member, sent at, amount
+const ADD_TO_SEASON: &str = "local start = tonumber(redis.call('HGET', KEYS[1], 'start')) or 0 \
+if tonumber(ARGV[2]) >= start then redis.call('ZINCRBY', KEYS[2], ARGV[3], ARGV[1]) end";
+//moves the current ranking into the archive and starts the next season, returns the number of the closed one
+//KEYS: season info, season ranking, archived seasons; ARGV: archive key prefix, now
+const CLOSE_SEASON: &str = "local number = tonumber(redis.call('HGET', KEYS[1], 'current')) or 1 \
+local start = redis.call('HGET', KEYS[1], 'start') or 0 \
+if redis.call('EXISTS', KEYS[2]) == 1 then redis.call('RENAME', KEYS[2], ARGV[1] .. number) end \
+redis.call('HSET', KEYS[3], number .. ':start', start, number .. ':end', ARGV[2]) \
+redis.call('HSET', KEYS[1], 'current', number + 1, 'start', ARGV[2]) \
+return number";
+
+pub struct Season {
+    pub number: u64,
+    ///Unix time the season started, None for the first season which started with the tracking.
+    pub start: Option<u64>,
+    ///Unix time the season was closed, None for the current season.
+    pub end: Option<u64>,
+}
+
+impl Season {
+    ///E.g. `2021-06-01 - 2021-07-01`.
+    pub fn describe(&self) -> String {
+        let date = |time: Option<u64>, missing: &str| match time {
+            Some(time) => Utc.timestamp(time as i64, 0).format("%Y-%m-%d").to_string(),
+            None => missing.to_string(),
+        };
+        format!(
+            "{} - {}",
+            date(self.start, "start of tracking"),
+            date(self.end, "now")
+        )
+    }
+}
+
+///Adds the message to the current season if it was sent after the season started.
+pub fn add_to_season(pipe: &mut Pipeline, message: &CountedMessage, amount: i64) {
+    pipe.cmd("EVAL")
+        .arg(ADD_TO_SEASON)
+        .arg(2)
+        .arg(info_key(message.guild_id))
+        .arg(season_key(message.guild_id))
+        .arg(message.member_id)
+        .arg(message.sent_at.timestamp())
+        .arg(amount)
+        .ignore();
+}
+
+pub fn current_season(con: &mut Connection, guild_id: u64) -> RedisResult<Season> {
+    let (number, start): (Option<u64>, Option<u64>) = redis::pipe()
+        .hget(info_key(guild_id), "current")
+        .hget(info_key(guild_id), "start")
+        .query(con)?;
+    Ok(Season {
+        number: number.unwrap_or(1),
+        start,
+        end: None,
+    })
+}
+
+///A closed season, None if there is no archive for it.
+pub fn archived_season(
+    con: &mut Connection,
+    guild_id: u64,
+    number: u64,
+) -> RedisResult<Option<Season>> {
+    let (start, end): (Option<u64>, Option<u64>) = redis::pipe()
+        .hget(archives_key(guild_id), format!("{}:start", number))
+        .hget(archives_key(guild_id), format!("{}:end", number))
+        .query(con)?;
+    Ok(end.map(|end| Season {
+        number,
+        start: start.filter(|start| *start > 0),
+        end: Some(end),
+    }))
+}
+
+///Archives the final standings of the current season and starts the next one, all time counts stay untouched.
+pub fn close_season(con: &mut Connection, guild_id: u64) -> RedisResult<u64> {
+    redis::cmd("EVAL")
+        .arg(CLOSE_SEASON)
+        .arg(3)
+        .arg(info_key(guild_id))
+        .arg(season_key(guild_id))
+        .arg(archives_key(guild_id))
+        .arg(format!("verifybot:season_archive:{}:", guild_id))
+        .arg(unix_now())
+        .query(con)
+}
+
+///Messages per member in the current season.
+pub fn season_key(guild_id: u64) -> String {
+    format!("verifybot:season:{}", guild_id)
+}
+
+///Final messages per member of a closed season.
+pub fn archive_key(guild_id: u64, number: u64) -> String {
+    format!("verifybot:season_archive:{}:{}", guild_id, number)
+}
+
+///Number and start of the current season.
+fn info_key(guild_id: u64) -> String {
+    format!("verifybot:season_info:{}", guild_id)
+}
+
+///Start and end of every closed season.
+fn archives_key(guild_id: u64) -> String {
+    format!("verifybot:seasons:{}", guild_id)
+}
@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use redis::{Commands, Connection, Pipeline, RedisResult};
+
+//KEYS: current streaks, longest streaks, last active day, active days of the member; ARGV: member, day
+//days can arrive out of order since the backfill walks backwards, so the run the day belongs to is measured
+const UPDATE_STREAK: &str = "local last = tonumber(redis.call('HGET', KEYS[3], ARGV[1])) \
+local day = tonumber(ARGV[2]) \
+if last and redis.call('EXISTS', KEYS[4]) == 0 then \
+local current = tonumber(redis.call('ZSCORE', KEYS[1], ARGV[1])) or 1 \
+for active = last - current + 1, last do redis.call('ZADD', KEYS[4], active, active) end end \
+if redis.call('ZADD', KEYS[4], day, day) == 0 then return end \
+local before = 0 \
+while redis.call('ZSCORE', KEYS[4], day - before - 1) do before = before + 1 end \
+local after = 0 \
+while redis.call('ZSCORE', KEYS[4], day + after + 1) do after = after + 1 end \
+local run = before + 1 + after \
+if not last or day > last then last = day redis.call('HSET', KEYS[3], ARGV[1], day) end \
+if day + after == last then redis.call('ZADD', KEYS[1], run, ARGV[1]) end \
+local longest = tonumber(redis.call('ZSCORE', KEYS[2], ARGV[1])) \
+if not longest or run > longest then redis.call('ZADD', KEYS[2], run, ARGV[1]) end";
+
+//how many stored streaks are read at once while skipping broken ones
+const BATCH_SIZE: isize = 50;
+
+pub struct Streak {
+    ///Consecutive days up to today or yesterday with at least one counted message.
+    pub current: u64,
+    pub longest: u64,
+}
+
+///Marks the day of the message as active for the member and updates their streaks,
+///days from before the last active one still join up runs for the backfill.
+pub fn record_activity(pipe: &mut Pipeline, guild_id: u64, member_id: u64, sent_at: DateTime<Utc>) {
+    pipe.cmd("EVAL")
+        .arg(UPDATE_STREAK)
+        .arg(4)
+        .arg(current_key(guild_id))
+        .arg(longest_key(guild_id))
+        .arg(last_day_key(guild_id))
+        .arg(active_days_key(guild_id, member_id))
+        .arg(member_id)
+        .arg(day_number(sent_at))
+        .ignore();
+}
+
+pub fn get_streak(con: &mut Connection, guild_id: u64, member_id: u64) -> RedisResult<Streak> {
+    let (current, longest, last_day): (Option<u64>, Option<u64>, Option<i64>) = redis::pipe()
+        .zscore(current_key(guild_id), member_id)
+        .zscore(longest_key(guild_id), member_id)
+        .hget(last_day_key(guild_id), member_id)
+        .query(con)?;
+    let current = match last_day {
+        Some(last_day) if is_ongoing(last_day) => current.unwrap_or(0),
+        _ => 0,
+    };
+    Ok(Streak {
+        current,
+        longest: longest.unwrap_or(0),
+    })
+}
+
+///The `limit` members with the highest streaks, either the ones that are still going or the longest ever.
+///Only the `members` still on the server are included.
+pub fn top_streaks(
+    con: &mut Connection,
+    guild_id: u64,
+    longest: bool,
+    members: &[u64],
+    limit: usize,
+) -> RedisResult<Vec<(u64, u64)>> {
+    let members: HashSet<u64> = members.iter().copied().collect();
+    let key = if longest {
+        longest_key(guild_id)
+    } else {
+        current_key(guild_id)
+    };
+    //members that left are skipped, so are broken current streaks since they stay until the member is active again
+    let mut top = Vec::new();
+    let mut start = 0;
+    while top.len() < limit {
+        let batch: Vec<(u64, u64)> =
+            con.zrevrange_withscores(&key, start, start + BATCH_SIZE - 1)?;
+        if batch.is_empty() {
+            break;
+        }
+        let batch_members: Vec<u64> = batch.iter().map(|(member_id, _)| *member_id).collect();
+        let ongoing: Vec<bool> = if longest {
+            vec![true; batch.len()]
+        } else {
+            let last_days: Vec<Option<i64>> = redis::cmd("HMGET")
+                .arg(last_day_key(guild_id))
+                .arg(batch_members)
+                .query(con)?;
+            last_days
+                .into_iter()
+                .map(|last_day| last_day.map(is_ongoing).unwrap_or(false))
+                .collect()
+        };
+        for ((member_id, streak), ongoing) in batch.into_iter().zip(ongoing) {
+            if ongoing && members.contains(&member_id) && top.len() < limit {
+                top.push((member_id, streak));
+            }
+        }
+        start += BATCH_SIZE;
+    }
+    Ok(top)
+}
+
+fn is_ongoing(last_day: i64) -> bool {
+    last_day >= day_number(Utc::now()) - 1
+}
+
+///Days since the unix epoch in UTC.
+fn day_number(time: DateTime<Utc>) -> i64 {
+    time.timestamp().div_euclid(86400)
+}
+
+fn current_key(guild_id: u64) -> String {
+    format!("verifybot:streak_current:{}", guild_id)
+}
+
+fn longest_key(guild_id: u64) -> String {
+    format!("verifybot:streak_longest:{}", guild_id)
+}
+
+fn last_day_key(guild_id: u64) -> String {
+    format!("verifybot:streak_day:{}", guild_id)
+}
+
+///Day numbers the member sent at least one counted message on. Streaks from before these were
+///stored are filled in from the current streak when the member is active the next time.
+fn active_days_key(guild_id: u64, member_id: u64) -> String {
+    format!("verifybot:streak_days:{}:{}", guild_id, member_id)
+}
@@ -0,0 +1,286 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use redis::{Commands, RedisError, RedisResult};
+use serenity::client::Context;
+use serenity::model::guild::Guild;
+use serenity::model::id::GuildId;
+use tokio::join;
+
+use crate::commands::members::{current_members, RANK_ROLES};
+use crate::features::links::{self, Link};
+use crate::features::message_counting::unix_now;
+use crate::hypixel::error::ApiError;
+use crate::{HYPIXEL_CLIENT, REDIS_CLIENT, VERIFIED_ROLE};
+
+//how often every link is synced
+const SYNC_INTERVAL: u64 = 12 * 60 * 60;
+//how often the job checks whether a sync is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+//Hypixel requests needed to sync one member
+const REQUESTS_PER_MEMBER: u32 = 2;
+//the sync uses at most a quarter of the per minute quota, the rest is left to commands
+const QUOTA_SHARE: u32 = 4;
+const QUOTA_WINDOW: Duration = Duration::from_secs(60);
+//the sync waits while commands are queued for the quota
+const BUSY_WAIT: Duration = Duration::from_secs(15);
+const GUILD_MEMBER_ROLE: &str = "Guild Member";
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+//only one guild is synced at a time so the syncs together stay within their share of the quota
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug)]
+pub enum SyncError {
+    ///The member never verified or verified before links were stored.
+    NotLinked,
+    Redis(RedisError),
+    Api(ApiError),
+    Discord(serenity::Error),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::NotLinked => write!(f, "no stored link"),
+            SyncError::Redis(err) => write!(f, "redis error: {}", err),
+            SyncError::Api(err) => write!(f, "{}", err),
+            SyncError::Discord(err) => write!(f, "Discord error: {}", err),
+        }
+    }
+}
+
+impl From<RedisError> for SyncError {
+    fn from(err: RedisError) -> Self {
+        SyncError::Redis(err)
+    }
+}
+
+impl From<ApiError> for SyncError {
+    fn from(err: ApiError) -> Self {
+        SyncError::Api(err)
+    }
+}
+
+impl From<serenity::Error> for SyncError {
+    fn from(err: serenity::Error) -> Self {
+        SyncError::Discord(err)
+    }
+}
+
+///Outcome of syncing every link of a guild.
+#[derive(Default)]
+pub struct SyncSummary {
+    pub synced: usize,
+    pub changed: usize,
+    pub failed: usize,
+}
+
+///Syncs every stored link once every `SYNC_INTERVAL`, the last run is stored so restarts don't start over.
+pub async fn run(ctx: Context) {
+    //ready fires again after reconnects
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    loop {
+        let last: RedisResult<Option<u64>> = REDIS_CLIENT
+            .get_connection()
+            .and_then(|mut con| con.get(last_sync_key()));
+        match last {
+            Ok(last) if unix_now().saturating_sub(last.unwrap_or(0)) >= SYNC_INTERVAL => {
+                for guild_id in ctx.cache.guilds().await {
+                    //waits for a resync started by a command to finish
+                    let summary = loop {
+                        match sync_guild(&ctx, guild_id).await {
+                            Some(summary) => break summary,
+                            None => tokio::time::sleep(BUSY_WAIT).await,
+                        }
+                    };
+                    println!(
+                        "Resynced {} links in {}: {} changed, {} failed",
+                        summary.synced, guild_id, summary.changed, summary.failed
+                    );
+                }
+                let stored: RedisResult<()> = REDIS_CLIENT
+                    .get_connection()
+                    .and_then(|mut con| con.set(last_sync_key(), unix_now()));
+                if let Err(err) = stored {
+                    println!("Error while storing the last resync: {}", err);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => println!("Error while checking the last resync: {}", err),
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+///Syncs every linked member of the guild, None if a guild is already being synced.
+pub async fn sync_guild(ctx: &Context, guild_id: GuildId) -> Option<SyncSummary> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+    let mut summary = SyncSummary::default();
+    let members = REDIS_CLIENT
+        .get_connection()
+        .and_then(|mut con| links::linked_members(&mut con, guild_id.0));
+    let current = current_members(ctx, guild_id).await;
+    match (members, current, guild_id.to_guild_cached(&ctx).await) {
+        (Ok(members), Ok(current), Some(guild)) => {
+            //links of members that left stay stored in case they come back
+            let current: HashSet<u64> = current.iter().map(|member| member.user.id.0).collect();
+            for member_id in members.into_iter().filter(|id| current.contains(id)) {
+                while HYPIXEL_CLIENT.is_rate_limited() {
+                    tokio::time::sleep(BUSY_WAIT).await;
+                }
+                summary.synced += 1;
+                let sent = HYPIXEL_CLIENT.requests_sent();
+                match sync_member(ctx, &guild, member_id).await {
+                    Ok(changes) if !changes.is_empty() => summary.changed += 1,
+                    Ok(_) => {}
+                    Err(_) => summary.failed += 1,
+                }
+                //members answered from the cache or skipped before asking Hypixel don't use the quota
+                if HYPIXEL_CLIENT.requests_sent() > sent {
+                    tokio::time::sleep(member_spacing()).await;
+                }
+            }
+        }
+        (Err(err), _, _) => println!("Error while reading the links of {}: {}", guild_id, err),
+        (_, Err(err), _) => println!("Error while reading the members of {}: {}", guild_id, err),
+        (_, _, None) => println!("Guild {} isn't cached, skipping the resync", guild_id),
+    }
+    RUNNING.store(false, Ordering::SeqCst);
+    Some(summary)
+}
+
+///Pause between two members so the sync stays within its share of the quota of the key.
+fn member_spacing() -> Duration {
+    let limit = HYPIXEL_CLIENT.rate_limit().max(1);
+    QUOTA_WINDOW * REQUESTS_PER_MEMBER * QUOTA_SHARE / limit
+}
+
+///Applies the current rank role, nickname and Guild Member role of the member's linked account
+///and returns what changed. Members whose Verified role was taken away are left alone.
+pub async fn sync_member(
+    ctx: &Context,
+    guild: &Guild,
+    member_id: u64,
+) -> Result<Vec<String>, SyncError> {
+    let result = apply(ctx, guild, member_id).await;
+    match &result {
+        Ok(changes) if !changes.is_empty() => println!(
+            "Resynced {} in {}: {}",
+            member_id,
+            guild.id,
+            changes.join(", ")
+        ),
+        Ok(_) => {}
+        Err(err) => println!(
+            "Error while resyncing {} in {}: {}",
+            member_id, guild.id, err
+        ),
+    }
+    result
+}
+
+async fn apply(ctx: &Context, guild: &Guild, member_id: u64) -> Result<Vec<String>, SyncError> {
+    let mut con = REDIS_CLIENT.get_connection()?;
+    let link = links::get_link(&mut con, guild.id.0, member_id)?.ok_or(SyncError::NotLinked)?;
+    let minecraft_guild: Option<String> =
+        con.hget(format!("verifybot:config:{}", guild.id), "minecraft_guild")?;
+    let member = guild.id.member(&ctx, member_id).await?;
+    let has_role = |name: &str| {
+        guild
+            .role_by_name(name)
+            .map(|role| member.roles.contains(&role.id))
+    };
+    let mut changes = Vec::new();
+    if has_role(VERIFIED_ROLE.as_str()) != Some(true) {
+        return Ok(changes);
+    }
+
+    let (player, hypixel_guild) = join!(
+        HYPIXEL_CLIENT.player(&link.uuid, guild.id.0),
+        HYPIXEL_CLIENT.guild_of(&link.uuid, guild.id.0)
+    );
+    let player = player?;
+
+    let rank = player.rank().role_name();
+    for name in RANK_ROLES.iter() {
+        let role = match guild.role_by_name(name) {
+            Some(role) => role.id,
+            None => continue,
+        };
+        let has = member.roles.contains(&role);
+        if Some(*name) == rank && !has {
+            ctx.http
+                .add_member_role(guild.id.0, member_id, role.0)
+                .await?;
+            changes.push(format!("added {}", name));
+        } else if Some(*name) != rank && has {
+            ctx.http
+                .remove_member_role(guild.id.0, member_id, role.0)
+                .await?;
+            changes.push(format!("removed {}", name));
+        }
+    }
+
+    let username = player.displayname.clone().unwrap_or(link.username.clone());
+    if member.nick.as_deref() != Some(username.as_str()) {
+        //members above the bot can't be renamed, the roles are still synced for them
+        let edited = guild
+            .id
+            .edit_member(&ctx.http, member_id, |m| m.nickname(&username))
+            .await;
+        match edited {
+            Ok(_) => changes.push(format!(
+                "nickname {} -> {}",
+                member.display_name(),
+                username
+            )),
+            Err(err) => println!(
+                "Error while renaming {} in {} to {}: {}",
+                member_id, guild.id, username, err
+            ),
+        }
+    }
+
+    //a failed guild lookup leaves the role as it is
+    if let (Some(minecraft_guild), Ok(hypixel_guild)) = (minecraft_guild, hypixel_guild) {
+        let in_guild =
+            matches!(hypixel_guild, Some(hypixel_guild) if hypixel_guild.name == minecraft_guild);
+        if let Some(role) = guild.role_by_name(GUILD_MEMBER_ROLE) {
+            let has = member.roles.contains(&role.id);
+            if in_guild && !has {
+                ctx.http
+                    .add_member_role(guild.id.0, member_id, role.id.0)
+                    .await?;
+                changes.push(format!("added {}", GUILD_MEMBER_ROLE));
+            } else if !in_guild && has {
+                ctx.http
+                    .remove_member_role(guild.id.0, member_id, role.id.0)
+                    .await?;
+                changes.push(format!("removed {}", GUILD_MEMBER_ROLE));
+            }
+        }
+    }
+
+    let rank = rank.map(|rank| rank.to_string());
+    if username != link.username || rank != link.rank {
+        let updated = Link {
+            username,
+            rank,
+            ..link
+        };
+        links::set_link(&mut con, guild.id.0, member_id, &updated)?;
+    }
+    Ok(changes)
+}
+
+///Unix time the last sync of every link finished.
+fn last_sync_key() -> String {
+    "verifybot:last_resync".to_string()
+}
@@ -0,0 +1,143 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use redis::{Commands, Connection, RedisResult};
+use serenity::model::id::ChannelId;
+use serenity::model::voice::VoiceState;
+
+use crate::features::message_counting::unix_now;
+use crate::{REDIS_CLIENT, VOICE_EXCLUDE_MUTED};
+
+//ends a session and returns its length, only the call that removes the session gets it
+const END_SESSION: &str = "local start = redis.call('HGET', KEYS[1], ARGV[1]) \
+if not start then return 0 end \
+redis.call('HDEL', KEYS[1], ARGV[1]) \
+local elapsed = tonumber(ARGV[2]) - tonumber(start) \
+if elapsed > 0 then redis.call('ZINCRBY', KEYS[2], elapsed, ARGV[1]) end \
+return elapsed";
+
+///Starts or ends the voice session of a member depending on their new voice state.
+pub async fn handle_voice_state(guild_id: u64, afk_channel: Option<ChannelId>, state: VoiceState) {
+    let con = REDIS_CLIENT.get_connection();
+    if con.is_err() {
+        println!("Error while opening redis connection");
+        return;
+    }
+    let mut con = con.unwrap();
+    let result = if is_counted(&state, afk_channel) {
+        start_session(&mut con, guild_id, state.user_id.0)
+    } else {
+        end_session(&mut con, guild_id, state.user_id.0)
+    };
+    if let Err(err) = result {
+        println!("Error while tracking voice activity: {}", err);
+    }
+}
+
+///Brings the stored sessions in line with the voice states after a (re)connect.
+///Sessions of members that left while the bot was offline are dropped because their end is unknown.
+pub async fn reconcile(guild_id: u64, afk_channel: Option<ChannelId>, states: Vec<VoiceState>) {
+    let con = REDIS_CLIENT.get_connection();
+    if con.is_err() {
+        println!("Error while opening redis connection");
+        return;
+    }
+    let mut con = con.unwrap();
+    let counted: HashSet<u64> = states
+        .iter()
+        .filter(|state| is_counted(state, afk_channel))
+        .map(|state| state.user_id.0)
+        .collect();
+    let stored: RedisResult<Vec<u64>> = con.hkeys(sessions_key(guild_id));
+    if let Ok(stored) = stored {
+        for member_id in stored.iter().filter(|id| !counted.contains(id)) {
+            let _: RedisResult<()> = con.hdel(sessions_key(guild_id), *member_id);
+        }
+    }
+    for member_id in counted {
+        let _ = start_session(&mut con, guild_id, member_id);
+    }
+}
+
+///Seconds the member spent in voice channels, including the session that is still running.
+pub fn get_voice_time(con: &mut Connection, guild_id: u64, member_id: u64) -> RedisResult<u64> {
+    let (total, start): (Option<u64>, Option<u64>) = redis::pipe()
+        .zscore(voice_key(guild_id), member_id)
+        .hget(sessions_key(guild_id), member_id)
+        .query(con)?;
+    let running = start.map_or(0, |start| unix_now().saturating_sub(start));
+    Ok(total.unwrap_or(0) + running)
+}
+
+///The members with the most time in voice channels, running sessions included like in `get_voice_time`.
+///Only the `members` still on the server are included.
+pub fn top_voice_times(
+    con: &mut Connection,
+    guild_id: u64,
+    members: &[u64],
+    limit: usize,
+) -> RedisResult<Vec<(u64, u64)>> {
+    let (totals, sessions): (HashMap<u64, u64>, HashMap<u64, u64>) = redis::pipe()
+        .zrange_withscores(voice_key(guild_id), 0, -1)
+        .hgetall(sessions_key(guild_id))
+        .query(con)?;
+    let now = unix_now();
+    let mut times = totals;
+    for (member_id, start) in sessions {
+        *times.entry(member_id).or_insert(0) += now.saturating_sub(start);
+    }
+    let members: HashSet<u64> = members.iter().copied().collect();
+    let mut times: Vec<(u64, u64)> = times
+        .into_iter()
+        .filter(|(member_id, _)| members.contains(member_id))
+        .collect();
+    times.sort_by_key(|(member_id, seconds)| (Reverse(*seconds), *member_id));
+    times.truncate(limit);
+    Ok(times)
+}
+
+///Formats seconds as hours and minutes, e.g. `3h 12m`.
+pub fn format_duration(seconds: u64) -> String {
+    format!("{}h {}m", seconds / 3600, seconds % 3600 / 60)
+}
+
+fn is_counted(state: &VoiceState, afk_channel: Option<ChannelId>) -> bool {
+    //bots idling in voice would top the leaderboard
+    if matches!(&state.member, Some(member) if member.user.bot) {
+        return false;
+    }
+    let channel = match state.channel_id {
+        Some(channel) => channel,
+        None => return false,
+    };
+    if Some(channel) == afk_channel {
+        return false;
+    }
+    let muted = state.mute || state.self_mute || state.deaf || state.self_deaf;
+    !(*VOICE_EXCLUDE_MUTED && muted)
+}
+
+fn start_session(con: &mut Connection, guild_id: u64, member_id: u64) -> RedisResult<()> {
+    con.hset_nx(sessions_key(guild_id), member_id, unix_now())
+}
+
+fn end_session(con: &mut Connection, guild_id: u64, member_id: u64) -> RedisResult<()> {
+    redis::cmd("EVAL")
+        .arg(END_SESSION)
+        .arg(2)
+        .arg(sessions_key(guild_id))
+        .arg(voice_key(guild_id))
+        .arg(member_id)
+        .arg(unix_now())
+        .query(con)
+}
+
+///Seconds spent in voice channels per member.
+fn voice_key(guild_id: u64) -> String {
+    format!("verifybot:voice:{}", guild_id)
+}
+
+///Start of the running voice session per member.
+fn sessions_key(guild_id: u64) -> String {
+    format!("verifybot:voice_sessions:{}", guild_id)
+}
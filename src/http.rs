@@ -0,0 +1,31 @@
+use std::time::Instant;
+
+use reqwest::{RequestBuilder, Response};
+
+use crate::HTTP_CLIENT;
+
+///Sends the request with the shared client and logs how long it took and the status that came back.
+///Only the host and path are logged since the query can contain secrets.
+pub async fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+    let request = request.build()?;
+    let method = request.method().clone();
+    let target = format!(
+        "{}{}",
+        request.url().host_str().unwrap_or(""),
+        request.url().path()
+    );
+    let started = Instant::now();
+    let result = HTTP_CLIENT.execute(request).await;
+    let elapsed = started.elapsed().as_millis();
+    match &result {
+        Ok(response) => println!(
+            "{} {} -> {} in {}ms",
+            method,
+            target,
+            response.status(),
+            elapsed
+        ),
+        Err(err) => println!("{} {} failed after {}ms: {}", method, target, elapsed, err),
+    }
+    result
+}
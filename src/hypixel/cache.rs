@@ -0,0 +1,182 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use redis::{Commands, RedisResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::features::message_counting::unix_now;
+use crate::hypixel::error::ApiError;
+use crate::REDIS_CLIENT;
+
+//how long an outdated entry is kept to answer with when the API fails
+const STALE_SECONDS: u64 = 86400;
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+static STALE_HITS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy)]
+pub enum Endpoint {
+    ///Username to UUID, changes rarely.
+    Profile,
+    Player,
+    Guild,
+}
+
+pub const ENDPOINTS: [Endpoint; 3] = [Endpoint::Profile, Endpoint::Player, Endpoint::Guild];
+
+impl Endpoint {
+    pub fn from_arg(arg: &str) -> Option<Endpoint> {
+        match arg {
+            "profile" | "uuid" => Some(Endpoint::Profile),
+            "player" => Some(Endpoint::Player),
+            "guild" => Some(Endpoint::Guild),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Endpoint::Profile => "profile",
+            Endpoint::Player => "player",
+            Endpoint::Guild => "guild",
+        }
+    }
+
+    ///Seconds a response is used without asking the API again.
+    pub fn ttl(&self) -> u64 {
+        match self {
+            Endpoint::Profile => 86400,
+            Endpoint::Player => 300,
+            Endpoint::Guild => 600,
+        }
+    }
+}
+
+///Hit counters since the bot started.
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    ///Outdated entries that were used because the API failed.
+    pub stale_hits: u64,
+}
+
+///Answers from the cache while the entry is fresh, otherwise asks the API and stores the answer.
+///If the API fails an outdated entry is used instead as long as there is one.
+pub async fn cached<T, F, Fut>(endpoint: Endpoint, id: &str, fetch: F) -> Result<T, ApiError>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, ApiError>>,
+{
+    let key = entry_key(endpoint, id);
+    let stored = read(&key);
+    let stale = match stored {
+        Some((value, fetched_at)) if unix_now().saturating_sub(fetched_at) < endpoint.ttl() => {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+        stored => stored.map(|(value, _)| value),
+    };
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    match fetch().await {
+        Ok(value) => {
+            write(&key, endpoint, &value);
+            Ok(value)
+        }
+        //unknown usernames are an answer, not a failure
+        Err(ApiError::UnknownUsername) => Err(ApiError::UnknownUsername),
+        Err(err) => match stale {
+            Some(value) => {
+                println!(
+                    "Using a stale {} entry for {}: {}",
+                    endpoint.name(),
+                    id,
+                    err
+                );
+                STALE_HITS.fetch_add(1, Ordering::Relaxed);
+                Ok(value)
+            }
+            None => Err(err),
+        },
+    }
+}
+
+pub fn stats() -> CacheStats {
+    CacheStats {
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+        stale_hits: STALE_HITS.load(Ordering::Relaxed),
+    }
+}
+
+///True if the endpoint would be answered from the cache without asking the API.
+pub fn is_fresh(endpoint: Endpoint, id: &str) -> bool {
+    let fetched_at: RedisResult<Option<u64>> = REDIS_CLIENT
+        .get_connection()
+        .and_then(|mut con| con.hget(entry_key(endpoint, id), "fetched_at"));
+    matches!(fetched_at, Ok(Some(fetched_at)) if unix_now().saturating_sub(fetched_at) < endpoint.ttl())
+}
+
+///Number of stored entries of the endpoint, including outdated ones.
+pub fn entry_count(endpoint: Endpoint) -> RedisResult<usize> {
+    let mut con = REDIS_CLIENT.get_connection()?;
+    let keys: Vec<String> = con.scan_match(format!("{}*", prefix(endpoint)))?.collect();
+    Ok(keys.len())
+}
+
+///Deletes every entry of the endpoint and returns how many there were.
+pub fn flush(endpoint: Endpoint) -> RedisResult<usize> {
+    let mut con = REDIS_CLIENT.get_connection()?;
+    let keys: Vec<String> = con.scan_match(format!("{}*", prefix(endpoint)))?.collect();
+    let count = keys.len();
+    if count > 0 {
+        let _: () = con.del(keys)?;
+    }
+    Ok(count)
+}
+
+fn read<T: DeserializeOwned>(key: &str) -> Option<(T, u64)> {
+    let mut con = REDIS_CLIENT.get_connection().ok()?;
+    let (body, fetched_at): (Option<String>, Option<u64>) = redis::pipe()
+        .hget(key, "body")
+        .hget(key, "fetched_at")
+        .query(&mut con)
+        .ok()?;
+    //entries that don't match the current structs anymore are treated as missing
+    Some((serde_json::from_str(&body?).ok()?, fetched_at?))
+}
+
+fn write<T: Serialize>(key: &str, endpoint: Endpoint, value: &T) {
+    let body = match serde_json::to_string(value) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+    let result: RedisResult<()> = REDIS_CLIENT.get_connection().and_then(|mut con| {
+        redis::pipe()
+            .atomic()
+            .hset(key, "body", body)
+            .ignore()
+            .hset(key, "fetched_at", unix_now())
+            .ignore()
+            .expire(key, (endpoint.ttl() + STALE_SECONDS) as usize)
+            .ignore()
+            .query(&mut con)
+    });
+    if let Err(err) = result {
+        println!(
+            "Error while caching a {} response: {}",
+            endpoint.name(),
+            err
+        );
+    }
+}
+
+fn prefix(endpoint: Endpoint) -> String {
+    format!("verifybot:api_cache:{}:", endpoint.name())
+}
+
+fn entry_key(endpoint: Endpoint, id: &str) -> String {
+    format!("{}{}", prefix(endpoint), id.to_lowercase())
+}
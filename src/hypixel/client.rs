@@ -0,0 +1,274 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::hypixel::cache::{self, Endpoint};
+use crate::hypixel::error::ApiError;
+use crate::hypixel::models::{
+    Guild, GuildData, HypixelResponse, MojangProfile, Player, PlayerData,
+};
+use crate::hypixel::rate_limit::RateLimiter;
+use crate::hypixel::secret::ApiKey;
+use crate::{http, HTTP_CLIENT};
+
+///Client for the Mojang and Hypixel APIs, every request to them should go through this.
+pub struct HypixelClient {
+    ///Base URLs without a trailing slash, configurable so a local stand-in server can be used.
+    mojang_url: String,
+    hypixel_url: String,
+    api_key: ApiKey,
+    limiter: RateLimiter,
+    ///Hypixel requests sent since the start, cache hits don't count.
+    requests_sent: AtomicU64,
+    ///False keeps responses out of redis, for tests against a stand-in server.
+    use_cache: bool,
+}
+
+//a request is given up after this many 429 responses in a row
+const MAX_ATTEMPTS: usize = 3;
+
+impl HypixelClient {
+    pub fn new(mojang_url: &str, hypixel_url: &str, api_key: ApiKey) -> Self {
+        HypixelClient {
+            mojang_url: mojang_url.trim_end_matches('/').to_string(),
+            hypixel_url: hypixel_url.trim_end_matches('/').to_string(),
+            api_key,
+            limiter: RateLimiter::new(),
+            requests_sent: AtomicU64::new(0),
+            use_cache: true,
+        }
+    }
+
+    #[cfg(test)]
+    fn without_cache(mut self) -> Self {
+        self.use_cache = false;
+        self
+    }
+
+    ///Looks up the UUID and the correct capitalization of a username.
+    pub async fn profile(&self, username: &str) -> Result<MojangProfile, ApiError> {
+        //names come from editable nicknames and end up in the URL path
+        if !is_valid_username(username) {
+            return Err(ApiError::UnknownUsername);
+        }
+        self.cached(Endpoint::Profile, username, || self.fetch_profile(username))
+            .await
+    }
+
+    ///Hypixel requests are queued per guild once the quota of the key is used up.
+    pub async fn player(&self, uuid: &str, guild_id: u64) -> Result<Player, ApiError> {
+        let data: PlayerData = self
+            .cached(Endpoint::Player, uuid, || async move {
+                self.hypixel("player", &[("uuid", uuid)], guild_id).await
+            })
+            .await?;
+        data.player.ok_or(ApiError::PlayerNotFound)
+    }
+
+    ///The guild the player is in, None if they aren't in one.
+    pub async fn guild_of(&self, uuid: &str, guild_id: u64) -> Result<Option<Guild>, ApiError> {
+        let data: GuildData = self
+            .cached(Endpoint::Guild, uuid, || async move {
+                self.hypixel("guild", &[("player", uuid)], guild_id).await
+            })
+            .await?;
+        Ok(data.guild)
+    }
+
+    ///The place the guild's lookup of the endpoints for the UUID would get in the queue,
+    ///None if it is sent right away or answered from the cache.
+    pub fn queue_position(
+        &self,
+        guild_id: u64,
+        uuid: &str,
+        endpoints: &[Endpoint],
+    ) -> Option<usize> {
+        if self.use_cache
+            && endpoints
+                .iter()
+                .all(|endpoint| cache::is_fresh(*endpoint, uuid))
+        {
+            return None;
+        }
+        self.limiter.queue_position(guild_id)
+    }
+
+    ///True while Hypixel requests have to wait for the quota.
+    pub fn is_rate_limited(&self) -> bool {
+        self.limiter.is_waiting()
+    }
+
+    ///Number of Hypixel requests sent so far, to tell whether a lookup used the quota.
+    pub fn requests_sent(&self) -> u64 {
+        self.requests_sent.load(Ordering::Relaxed)
+    }
+
+    ///Requests the key may send per window, as last reported by Hypixel.
+    pub fn rate_limit(&self) -> u32 {
+        self.limiter.limit()
+    }
+
+    ///See `cache::cached`, asks the API every time if the cache is turned off.
+    async fn cached<T, F, Fut>(&self, endpoint: Endpoint, id: &str, fetch: F) -> Result<T, ApiError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ApiError>>,
+    {
+        if !self.use_cache {
+            return fetch().await;
+        }
+        cache::cached(endpoint, id, fetch).await
+    }
+
+    async fn fetch_profile(&self, username: &str) -> Result<MojangProfile, ApiError> {
+        let response = http::send(HTTP_CLIENT.get(format!(
+            "{}/users/profiles/minecraft/{}",
+            self.mojang_url, username
+        )))
+        .await?;
+        //unknown usernames get an empty or a not found response
+        if response.status() == StatusCode::NO_CONTENT || response.status() == StatusCode::NOT_FOUND
+        {
+            return Err(ApiError::UnknownUsername);
+        }
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    async fn hypixel<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        guild_id: u64,
+    ) -> Result<T, ApiError> {
+        let key = self.api_key.header_value().ok_or_else(|| {
+            ApiError::Hypixel("the API key contains invalid characters".to_string())
+        })?;
+        let mut attempts = 0;
+        let response = loop {
+            if attempts == MAX_ATTEMPTS {
+                return Err(ApiError::RateLimited);
+            }
+            attempts += 1;
+            self.limiter.acquire(guild_id).await;
+            let request = HTTP_CLIENT
+                .get(format!("{}/{}", self.hypixel_url, endpoint))
+                .header("API-Key", key.clone())
+                .query(query);
+            self.requests_sent.fetch_add(1, Ordering::Relaxed);
+            let response = http::send(request).await?;
+            self.limiter.update(response.headers());
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                break response;
+            }
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok());
+            self.limiter.exhausted(retry_after);
+        };
+        let response: HypixelResponse<T> = response.json().await?;
+        if !response.success {
+            //the cause is logged and shown, so it must not contain the key
+            let cause = response
+                .cause
+                .unwrap_or_else(|| "unknown cause".to_string());
+            return Err(ApiError::Hypixel(self.api_key.redact(&cause)));
+        }
+        Ok(response.data)
+    }
+}
+
+///Minecraft usernames are 1 to 16 letters, digits and underscores.
+fn is_valid_username(username: &str) -> bool {
+    (1..=16).contains(&username.len())
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    ///A client for a local stand-in of both APIs, which knows every username and UUID.
+    fn stand_in_client() -> HypixelClient {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let request = String::from_utf8_lossy(&request).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+                let body = respond(&path, request.contains("api-key: test-key"));
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nRateLimit-Limit: 120\r\nRateLimit-Remaining: 119\r\nRateLimit-Reset: 60\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        HypixelClient::new(&url, &url, ApiKey::new("test-key".to_string())).without_cache()
+    }
+
+    fn respond(path: &str, has_key: bool) -> String {
+        if let Some(name) = path.strip_prefix("/users/profiles/minecraft/") {
+            return format!(
+                r#"{{"id":"uuid{}","name":"{}"}}"#,
+                name.to_lowercase(),
+                name
+            );
+        }
+        if !has_key {
+            return r#"{"success":false,"cause":"Invalid API key"}"#.to_string();
+        }
+        match path.split('?').next() {
+            Some("/player") => r#"{"success":true,"player":{"displayname":"Stand_In","newPackageRank":"MVP_PLUS","socialMedia":{"links":{"DISCORD":"someone#1234"}}}}"#.to_string(),
+            Some("/guild") => r#"{"success":true,"guild":null}"#.to_string(),
+            _ => r#"{"success":false,"cause":"Unknown endpoint"}"#.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn looks_up_profiles_and_players_on_the_configured_server() {
+        let client = stand_in_client();
+        let profile = client.profile("Stand_In").await.unwrap();
+        assert_eq!(profile.name, "Stand_In");
+        assert_eq!(profile.id, "uuidstand_in");
+
+        let player = client.player(&profile.id, 1).await.unwrap();
+        assert_eq!(player.displayname.as_deref(), Some("Stand_In"));
+        assert_eq!(player.discord(), Some("someone#1234"));
+        assert!(client.guild_of(&profile.id, 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_usernames_that_would_change_the_url() {
+        let client = stand_in_client();
+        for name in &["a/../b", "name?x=1", "name#frag", "", "seventeen_chars_x"] {
+            assert!(matches!(
+                client.profile(name).await,
+                Err(ApiError::UnknownUsername)
+            ));
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::fmt;
+
+///Everything that can go wrong while talking to the Mojang or Hypixel API.
+#[derive(Debug)]
+pub enum ApiError {
+    ///The request failed or the response couldn't be parsed.
+    Request(reqwest::Error),
+    ///Mojang doesn't know an account with this username.
+    UnknownUsername,
+    ///Hypixel refused the request, e.g. because of an invalid API key.
+    Hypixel(String),
+    ///The account never joined Hypixel.
+    PlayerNotFound,
+    ///Hypixel kept answering with 429 even after waiting for the quota.
+    RateLimited,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Request(err) => write!(f, "request failed: {}", err),
+            ApiError::UnknownUsername => write!(f, "unknown Minecraft username"),
+            ApiError::Hypixel(cause) => write!(f, "Hypixel API error: {}", cause),
+            ApiError::PlayerNotFound => write!(f, "player never joined Hypixel"),
+            ApiError::RateLimited => write!(f, "Hypixel API rate limit exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        ApiError::Request(err)
+    }
+}
@@ -0,0 +1,6 @@
+pub mod cache;
+pub mod client;
+pub mod error;
+pub mod models;
+pub mod rate_limit;
+pub mod secret;
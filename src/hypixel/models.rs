@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+///Response of the Mojang username to profile endpoint.
+#[derive(Deserialize, Serialize)]
+pub struct MojangProfile {
+    ///UUID without dashes.
+    pub id: String,
+    pub name: String,
+}
+
+///Fields every Hypixel response has, `cause` is set if `success` is false.
+#[derive(Deserialize)]
+pub struct HypixelResponse<T> {
+    pub success: bool,
+    pub cause: Option<String>,
+    #[serde(flatten)]
+    pub data: T,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PlayerData {
+    ///Null for accounts that never joined.
+    pub player: Option<Player>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct GuildData {
+    ///Null if the player isn't in a guild.
+    pub guild: Option<Guild>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Player {
+    pub displayname: Option<String>,
+    ///Staff and youtube ranks.
+    pub rank: Option<String>,
+    ///`SUPERSTAR` for MVP++.
+    pub monthly_package_rank: Option<String>,
+    pub new_package_rank: Option<String>,
+    ///Custom prefixes like the one of the owner.
+    pub prefix: Option<String>,
+    pub social_media: Option<SocialMedia>,
+    pub network_exp: Option<f64>,
+    pub karma: Option<u64>,
+    pub achievement_points: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SocialMedia {
+    #[serde(default)]
+    pub links: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Guild {
+    pub name: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HypixelRank {
+    Default,
+    Vip,
+    VipPlus,
+    Mvp,
+    MvpPlus,
+    MvpPlusPlus,
+}
+
+impl HypixelRank {
+    ///Name of the role verify hands out for the rank.
+    pub fn role_name(&self) -> Option<&'static str> {
+        match self {
+            HypixelRank::Default => None,
+            HypixelRank::Vip => Some("VIP"),
+            HypixelRank::VipPlus => Some("VIP+"),
+            HypixelRank::Mvp => Some("MVP"),
+            HypixelRank::MvpPlus => Some("MVP+"),
+            HypixelRank::MvpPlusPlus => Some("MVP++"),
+        }
+    }
+}
+
+impl Player {
+    ///The purchased rank, players with a staff rank or a custom prefix count as default.
+    pub fn rank(&self) -> HypixelRank {
+        //based on my API Wrapper in Java: https://github.com/Lulonaut/HypixelAPIWrapper/blob/d43c73c00f2bc111cf407c6a325cb686a3ec899a/src/main/java/de/lulonaut/wrapper/utils/getStuff.java#L19
+        //check for owner and other weird ranks (eg: Technoblade Pig rank)
+        if self.prefix.is_some() {
+            return HypixelRank::Default;
+        }
+        //check for staff
+        if let Some("HELPER" | "MODERATOR" | "ADMIN" | "YOUTUBER") = self.rank.as_deref() {
+            return HypixelRank::Default;
+        }
+        if self.monthly_package_rank.as_deref() == Some("SUPERSTAR") {
+            return HypixelRank::MvpPlusPlus;
+        }
+        match self.new_package_rank.as_deref() {
+            Some("MVP_PLUS") => HypixelRank::MvpPlus,
+            Some("MVP") => HypixelRank::Mvp,
+            Some("VIP_PLUS") => HypixelRank::VipPlus,
+            Some("VIP") => HypixelRank::Vip,
+            _ => HypixelRank::Default,
+        }
+    }
+
+    ///The Discord tag the player linked on Hypixel.
+    pub fn discord(&self) -> Option<&str> {
+        self.social_media
+            .as_ref()?
+            .links
+            .get("DISCORD")
+            .map(String::as_str)
+    }
+
+    ///Network level calculated from the experience, every level needs 2500 more experience than the last.
+    pub fn network_level(&self) -> f64 {
+        (2.0 * self.network_exp.unwrap_or(0.0) + 30625.0).sqrt() / 50.0 - 2.5
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+//assumed until Hypixel tells the real values
+const DEFAULT_LIMIT: u32 = 120;
+const DEFAULT_RESET: Duration = Duration::from_secs(60);
+
+///Tracks the remaining quota of the API key and lets requests wait for the next window when it
+///is used up. Waiting requests are served round robin per guild so one busy guild can't starve the others.
+pub struct RateLimiter {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    limit: u32,
+    ///None until the first response arrived.
+    remaining: Option<u32>,
+    reset_at: Instant,
+    ///Waiting requests per guild, the guild in front is served next.
+    waiting: VecDeque<(u64, VecDeque<oneshot::Sender<()>>)>,
+    releasing: bool,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        RateLimiter {
+            state: Arc::new(Mutex::new(State::new(Instant::now()))),
+        }
+    }
+
+    ///Waits until the quota allows another request for the guild.
+    pub async fn acquire(&self, guild_id: u64) {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if state.waiting.is_empty() && state.take(Instant::now()) {
+                return;
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.enqueue(guild_id, sender);
+            if !state.releasing {
+                state.releasing = true;
+                tokio::spawn(release(self.state.clone()));
+            }
+            receiver
+        };
+        //the sender is only dropped after it was used
+        let _ = receiver.await;
+    }
+
+    ///The place a new request of the guild would get in the queue, None if it can be sent right away.
+    pub fn queue_position(&self, guild_id: u64) -> Option<usize> {
+        let state = self.state.lock().unwrap();
+        if !state.is_waiting(Instant::now()) {
+            return None;
+        }
+        Some(state.position(guild_id))
+    }
+
+    ///True while requests have to wait for the quota.
+    pub fn is_waiting(&self) -> bool {
+        self.state.lock().unwrap().is_waiting(Instant::now())
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.state.lock().unwrap().limit
+    }
+
+    ///Updates the quota from the `RateLimit-*` headers of a response.
+    pub fn update(&self, headers: &HeaderMap) {
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+        self.state.lock().unwrap().update(
+            number("RateLimit-Limit").map(|limit| limit as u32),
+            number("RateLimit-Remaining").map(|remaining| remaining as u32),
+            number("RateLimit-Reset").map(Duration::from_secs),
+            Instant::now(),
+        );
+    }
+
+    ///Marks the quota as used up after a 429 response, `retry_after` is in seconds.
+    pub fn exhausted(&self, retry_after: Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        state.remaining = Some(0);
+        let wait = retry_after.map_or(DEFAULT_RESET, Duration::from_secs);
+        state.reset_at = state.reset_at.max(Instant::now() + wait);
+    }
+}
+
+impl State {
+    fn new(now: Instant) -> Self {
+        State {
+            limit: DEFAULT_LIMIT,
+            remaining: None,
+            reset_at: now,
+            waiting: VecDeque::new(),
+            releasing: false,
+        }
+    }
+
+    fn available(&self, now: Instant) -> bool {
+        self.remaining != Some(0) || now >= self.reset_at
+    }
+
+    fn is_waiting(&self, now: Instant) -> bool {
+        !self.waiting.is_empty() || !self.available(now)
+    }
+
+    ///Uses one request of the quota, false if there is none left.
+    fn take(&mut self, now: Instant) -> bool {
+        if !self.available(now) {
+            return false;
+        }
+        if now >= self.reset_at {
+            self.remaining = Some(self.limit);
+            self.reset_at = now + DEFAULT_RESET;
+        }
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining -= 1;
+        }
+        true
+    }
+
+    fn update(
+        &mut self,
+        limit: Option<u32>,
+        remaining: Option<u32>,
+        reset: Option<Duration>,
+        now: Instant,
+    ) {
+        if let Some(limit) = limit {
+            self.limit = limit;
+        }
+        //responses of concurrent requests arrive in any order, within a window the lowest count is the newest
+        let same_window = now < self.reset_at && self.remaining.is_some();
+        if let Some(remaining) = remaining {
+            self.remaining = match self.remaining {
+                Some(local) if same_window => Some(local.min(remaining)),
+                _ => Some(remaining),
+            };
+        }
+        if let Some(reset) = reset {
+            self.reset_at = if same_window {
+                self.reset_at.max(now + reset)
+            } else {
+                now + reset
+            };
+        }
+    }
+
+    fn enqueue(&mut self, guild_id: u64, sender: oneshot::Sender<()>) {
+        match self.waiting.iter_mut().find(|(id, _)| *id == guild_id) {
+            Some((_, queue)) => queue.push_back(sender),
+            None => self
+                .waiting
+                .push_back((guild_id, VecDeque::from(vec![sender]))),
+        }
+    }
+
+    ///Takes the next waiting request, the guild it belongs to moves to the back of the line.
+    fn next_waiting(&mut self) -> Option<(u64, oneshot::Sender<()>)> {
+        let (guild_id, mut queue) = self.waiting.pop_front()?;
+        let sender = queue.pop_front();
+        if !queue.is_empty() {
+            self.waiting.push_back((guild_id, queue));
+        }
+        Some((guild_id, sender?))
+    }
+
+    fn position(&self, guild_id: u64) -> usize {
+        let own = self
+            .waiting
+            .iter()
+            .find(|(id, _)| *id == guild_id)
+            .map_or(0, |(_, queue)| queue.len());
+        //every guild gets one turn per round, guilds in front of this one also get the turn of the round the request is in
+        let mut in_front = true;
+        let mut ahead = 0;
+        for (id, queue) in &self.waiting {
+            if *id == guild_id {
+                in_front = false;
+                continue;
+            }
+            let rounds = if in_front { own + 1 } else { own };
+            ahead += queue.len().min(rounds);
+        }
+        own + ahead + 1
+    }
+}
+
+///Lets waiting requests through whenever a new window starts until nobody waits anymore.
+async fn release(shared: Arc<Mutex<State>>) {
+    loop {
+        let reset_at = shared.lock().unwrap().reset_at;
+        tokio::time::sleep_until(reset_at).await;
+        let mut state = shared.lock().unwrap();
+        while state.available(Instant::now()) {
+            let (_, sender) = match state.next_waiting() {
+                Some(next) => next,
+                None => break,
+            };
+            //requests whose command gave up don't use the quota
+            if sender.send(()).is_ok() {
+                state.take(Instant::now());
+            }
+        }
+        if state.waiting.is_empty() {
+            state.releasing = false;
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waiting(state: &mut State, guild_id: u64, requests: usize) {
+        for _ in 0..requests {
+            let (sender, _) = oneshot::channel();
+            state.enqueue(guild_id, sender);
+        }
+    }
+
+    #[test]
+    fn guilds_take_turns() {
+        let mut state = State::new(Instant::now());
+        waiting(&mut state, 1, 3);
+        waiting(&mut state, 2, 1);
+        waiting(&mut state, 3, 2);
+        let order: Vec<u64> = std::iter::from_fn(|| state.next_waiting())
+            .map(|(guild_id, _)| guild_id)
+            .collect();
+        assert_eq!(order, vec![1, 2, 3, 1, 3, 1]);
+    }
+
+    #[test]
+    fn position_counts_one_turn_per_guild_and_round() {
+        let mut state = State::new(Instant::now());
+        assert_eq!(state.position(1), 1);
+        waiting(&mut state, 1, 2);
+        waiting(&mut state, 2, 5);
+        waiting(&mut state, 3, 1);
+        //served as 1, 2, 3, 1, 2, then the new request of guild 1
+        assert_eq!(state.position(1), 6);
+        //served as 1, 2, 3, 1, 2, then the new request of guild 3
+        assert_eq!(state.position(3), 6);
+        //a guild that isn't waiting comes after one round of the others
+        assert_eq!(state.position(4), 4);
+    }
+
+    #[test]
+    fn take_stops_at_zero_until_the_reset() {
+        let now = Instant::now();
+        let mut state = State::new(now);
+        state.update(Some(2), Some(1), Some(Duration::from_secs(30)), now);
+        assert!(state.take(now));
+        assert!(!state.take(now));
+        assert!(state.is_waiting(now));
+        let later = now + Duration::from_secs(31);
+        assert!(state.take(later));
+        assert_eq!(state.remaining, Some(1));
+    }
+
+    #[test]
+    fn older_responses_dont_raise_the_remaining_quota() {
+        let now = Instant::now();
+        let mut state = State::new(now);
+        state.update(None, Some(10), Some(Duration::from_secs(40)), now);
+        state.update(None, Some(50), Some(Duration::from_secs(42)), now);
+        assert_eq!(state.remaining, Some(10));
+        //a new window takes the count of the server
+        let next_window = now + Duration::from_secs(45);
+        state.update(None, Some(50), Some(Duration::from_secs(60)), next_window);
+        assert_eq!(state.remaining, Some(50));
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+use reqwest::header::HeaderValue;
+
+///The Hypixel API key. It is only ever sent in a header and can't be printed, Debug shows it redacted.
+#[derive(Clone)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    pub fn new(key: String) -> Self {
+        ApiKey(key.trim().to_string())
+    }
+
+    ///Header value marked as sensitive so reqwest doesn't print it either, None if the key isn't a valid header value.
+    pub fn header_value(&self) -> Option<HeaderValue> {
+        let mut value = HeaderValue::from_str(&self.0).ok()?;
+        value.set_sensitive(true);
+        Some(value)
+    }
+
+    ///Replaces every occurrence of the key in text that is about to be logged or shown.
+    pub fn redact(&self, text: &str) -> String {
+        if self.0.is_empty() {
+            return text.to_string();
+        }
+        text.replace(&self.0, "<redacted>")
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ApiKey(<redacted>)")
+    }
+}
@@ -1,10 +1,17 @@
 use std::env;
+use std::time::Duration;
 
 use dotenv::dotenv;
 use lazy_static::lazy_static;
 use serenity::client::bridge::gateway::GatewayIntents;
 use serenity::model::prelude::Activity;
-use serenity::model::{channel::Message, gateway::Ready, guild::Member, id::GuildId};
+use serenity::model::{
+    channel::Message,
+    gateway::Ready,
+    guild::Member,
+    id::{ChannelId, GuildId, MessageId},
+    voice::VoiceState,
+};
 use serenity::Client;
 use serenity::{async_trait, prelude::*};
 use tokio::runtime::Runtime;
@@ -13,21 +20,56 @@ use crate::commands::command::Command;
 
 mod commands;
 mod features;
+mod http;
+mod hypixel;
 
 lazy_static! {
     static ref REDIS_CLIENT: redis::Client = redis::Client::open("redis://127.0.0.1/").unwrap();
     static ref TOKEN: String =
         env::var("DISCORD_TOKEN").expect("Please add a DISCORD_TOKEN to the .env");
     static ref PREFIX: String = env::var("PREFIX").expect("Please add a PREFIX to the .env");
-    static ref API_KEY: String =
-        env::var("HYPIXEL_API_KEY").expect("Please add a HYPIXEL_API_KEY to the .env");
+    static ref API_KEY: hypixel::secret::ApiKey = hypixel::secret::ApiKey::new(
+        env::var("HYPIXEL_API_KEY").expect("Please add a HYPIXEL_API_KEY to the .env")
+    );
+    //one client for every outgoing request so connections are reused
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .expect("Error while building the HTTP client");
+    static ref MOJANG_API_URL: String =
+        env::var("MOJANG_API_URL").unwrap_or_else(|_| "https://api.mojang.com".to_string());
+    static ref HYPIXEL_API_URL: String =
+        env::var("HYPIXEL_API_URL").unwrap_or_else(|_| "https://api.hypixel.net".to_string());
+    static ref HYPIXEL_CLIENT: hypixel::client::HypixelClient =
+        hypixel::client::HypixelClient::new(&MOJANG_API_URL, &HYPIXEL_API_URL, API_KEY.clone());
     static ref VERIFIED_ROLE: String = env::var("VERIFIED_ROLE")
         .expect("Please add a VERIFIED_ROLE to the .env")
         .replace("_", " ");
+    //seconds a counted message is remembered so deleting it can reverse the count
+    static ref COUNTED_MESSAGE_WINDOW: u64 = env::var("COUNTED_MESSAGE_WINDOW")
+        .ok()
+        .and_then(|window| window.parse().ok())
+        .unwrap_or(86400);
+    //don't count time in voice channels while muted or deafened
+    static ref VOICE_EXCLUDE_MUTED: bool = env::var("VOICE_EXCLUDE_MUTED")
+        .map(|exclude| exclude == "true")
+        .unwrap_or(false);
     static ref VERIFY_COMMAND: String = "verify".to_string();
     static ref LEADEARBORAD_COMMAND: String = "leaderboard".to_string();
     static ref LOOKUP_COMMAND: String = "lookup".to_string();
     static ref SET_GUILD_COMMAND: String = "setguild".to_string();
+    static ref BACKFILL_COMMAND: String = "backfill".to_string();
+    static ref STATS_COMMAND: String = "stats".to_string();
+    static ref STREAKS_COMMAND: String = "streaks".to_string();
+    static ref INACTIVE_COMMAND: String = "inactive".to_string();
+    static ref VOICE_LEADERBOARD_COMMAND: String = "voiceleaderboard".to_string();
+    static ref SCHEDULE_COMMAND: String = "schedule".to_string();
+    static ref SET_TIMEZONE_COMMAND: String = "settimezone".to_string();
+    static ref SEASON_COMMAND: String = "season".to_string();
+    static ref PROFILE_COMMAND: String = "profile".to_string();
+    static ref COMPARE_COMMAND: String = "compare".to_string();
+    static ref API_CACHE_COMMAND: String = "apicache".to_string();
+    static ref RESYNC_COMMAND: String = "resync".to_string();
     static ref MESSAGE_LOOKUP_EXECUTOR: commands::message_lookup::CommandArgs =
         commands::message_lookup::CommandArgs {
             prefix: PREFIX.to_string(),
@@ -37,7 +79,6 @@ lazy_static! {
         commands::verify_command::VerifyCommandArgs {
             prefix: PREFIX.to_string(),
             command: VERIFY_COMMAND.to_string(),
-            api_key: API_KEY.to_string(),
             role_name: VERIFIED_ROLE.to_string()
         };
     static ref LEADERBOARD_COMMAND_EXECUTER: commands::message_leaderboard::CommandArgs = {
@@ -52,6 +93,79 @@ lazy_static! {
             command: SET_GUILD_COMMAND.to_string(),
         }
     };
+    static ref BACKFILL_COMMAND_EXECUTER: commands::backfill::CommandArgs = {
+        commands::backfill::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: BACKFILL_COMMAND.to_string(),
+        }
+    };
+    static ref STATS_COMMAND_EXECUTER: commands::stats::CommandArgs = {
+        commands::stats::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: STATS_COMMAND.to_string(),
+        }
+    };
+    static ref STREAKS_COMMAND_EXECUTER: commands::streaks::CommandArgs = {
+        commands::streaks::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: STREAKS_COMMAND.to_string(),
+        }
+    };
+    static ref INACTIVE_COMMAND_EXECUTER: commands::inactive::CommandArgs = {
+        commands::inactive::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: INACTIVE_COMMAND.to_string(),
+        }
+    };
+    static ref VOICE_LEADERBOARD_COMMAND_EXECUTER: commands::voice_leaderboard::CommandArgs = {
+        commands::voice_leaderboard::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: VOICE_LEADERBOARD_COMMAND.to_string(),
+        }
+    };
+    static ref SCHEDULE_COMMAND_EXECUTER: commands::schedule::CommandArgs = {
+        commands::schedule::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: SCHEDULE_COMMAND.to_string(),
+        }
+    };
+    static ref SET_TIMEZONE_COMMAND_EXECUTER: commands::set_timezone::CommandArgs = {
+        commands::set_timezone::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: SET_TIMEZONE_COMMAND.to_string(),
+        }
+    };
+    static ref SEASON_COMMAND_EXECUTER: commands::season::CommandArgs = {
+        commands::season::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: SEASON_COMMAND.to_string(),
+            leaderboard_command: LEADEARBORAD_COMMAND.to_string(),
+        }
+    };
+    static ref PROFILE_COMMAND_EXECUTER: commands::profile::CommandArgs = {
+        commands::profile::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: PROFILE_COMMAND.to_string(),
+        }
+    };
+    static ref COMPARE_COMMAND_EXECUTER: commands::compare::CommandArgs = {
+        commands::compare::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: COMPARE_COMMAND.to_string(),
+        }
+    };
+    static ref API_CACHE_COMMAND_EXECUTER: commands::api_cache::CommandArgs = {
+        commands::api_cache::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: API_CACHE_COMMAND.to_string(),
+        }
+    };
+    static ref RESYNC_COMMAND_EXECUTER: commands::resync::CommandArgs = {
+        commands::resync::CommandArgs {
+            prefix: PREFIX.to_string(),
+            command: RESYNC_COMMAND.to_string(),
+        }
+    };
 }
 
 async fn say_something(message: String, ctx: Context, msg: Message) {
@@ -75,21 +189,109 @@ impl EventHandler for Handler {
             return;
         }
         //handle message addition async
-        let handle =
-            features::message_counting::handle_messages_redis(msg.member(&ctx).await.unwrap());
-        tokio::spawn(async { handle.await });
+        if let Some(guild_id) = msg.guild_id {
+            let counted = features::message_counting::CountedMessage {
+                guild_id: guild_id.0,
+                channel_id: msg.channel_id.0,
+                member_id: msg.author.id.0,
+                sent_at: msg.timestamp,
+            };
+            let handle = features::message_counting::handle_messages_redis(counted, msg.id.0);
+            tokio::spawn(async { handle.await });
+        }
 
         //execute commands
         LEADERBOARD_COMMAND_EXECUTER.execute(&ctx, &msg).await;
         VERIFY_COMMAND_EXECUTER.execute(&ctx, &msg).await;
         MESSAGE_LOOKUP_EXECUTOR.execute(&ctx, &msg).await;
         SET_GUILD_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        BACKFILL_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        STATS_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        STREAKS_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        INACTIVE_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        VOICE_LEADERBOARD_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        SCHEDULE_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        SET_TIMEZONE_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        SEASON_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        PROFILE_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        COMPARE_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        API_CACHE_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+        RESYNC_COMMAND_EXECUTER.execute(&ctx, &msg).await;
+    }
+
+    async fn message_delete(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Some(guild_id) = guild_id {
+            features::message_counting::handle_message_deletion(
+                guild_id.0,
+                channel_id.0,
+                vec![deleted_message_id.0],
+            )
+            .await;
+        }
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Some(guild_id) = guild_id {
+            let ids = multiple_deleted_messages_ids
+                .iter()
+                .map(|id| id.0)
+                .collect();
+            features::message_counting::handle_message_deletion(guild_id.0, channel_id.0, ids)
+                .await;
+        }
+    }
+
+    async fn voice_state_update(
+        &self,
+        ctx: Context,
+        guild_id: Option<GuildId>,
+        _old: Option<VoiceState>,
+        new: VoiceState,
+    ) {
+        if let Some(guild_id) = guild_id {
+            let afk_channel = guild_id
+                .to_guild_cached(&ctx)
+                .await
+                .and_then(|guild| guild.afk_channel_id);
+            features::voice_tracking::handle_voice_state(guild_id.0, afk_channel, new).await;
+        }
+    }
+
+    async fn cache_ready(&self, ctx: Context, guilds: Vec<GuildId>) {
+        for guild_id in guilds {
+            if let Some(guild) = guild_id.to_guild_cached(&ctx).await {
+                //the states of a cached guild don't carry the member, so bots are filtered here
+                let states = guild
+                    .voice_states
+                    .values()
+                    .filter(|state| {
+                        !matches!(guild.members.get(&state.user_id), Some(member) if member.user.bot)
+                    })
+                    .cloned()
+                    .collect();
+                features::voice_tracking::reconcile(guild_id.0, guild.afk_channel_id, states).await;
+            }
+        }
     }
 
     async fn ready(&self, ctx: Context, ready: Ready) {
         ctx.set_activity(Activity::watching("https://github.com/Lulonaut/rustbot"))
             .await;
         println!("Connected as {}", ready.user.name);
+        tokio::spawn(features::schedule::run(ctx.clone()));
+        tokio::spawn(features::sync::run(ctx));
     }
 }
 
@@ -100,9 +302,22 @@ async fn main() {
     let _ = rt.enter();
 
     dotenv().expect("please add a .env");
+    //counts have to be converted before the first message arrives
+    match REDIS_CLIENT.get_connection() {
+        Ok(mut con) => {
+            if let Err(err) = features::leaderboard::migrate_hashes(&mut con) {
+                println!("Error while migrating message counts: {}", err);
+            }
+        }
+        Err(err) => println!("Error while opening redis connection: {}", err),
+    }
     let mut client = Client::builder(TOKEN.to_string())
         .intents(
-            GatewayIntents::GUILD_MEMBERS | GatewayIntents::GUILD_MESSAGES | GatewayIntents::GUILDS,
+            GatewayIntents::GUILD_MEMBERS
+                | GatewayIntents::GUILD_MESSAGES
+                | GatewayIntents::GUILDS
+                | GatewayIntents::GUILD_VOICE_STATES
+                | GatewayIntents::GUILD_MESSAGE_REACTIONS,
         )
         .event_handler(Handler)
         .await